@@ -1,10 +1,20 @@
-use std::process::Command;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Arc,
+};
 
 use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
+use crate::limits::AgentLimiter;
 use crate::models::AgentSession;
 
-pub trait AgentHarness {
+pub trait AgentHarness: Send + Sync {
+    fn name(&self) -> &str;
     fn run(&self, prompt: &str) -> anyhow::Result<String>;
 }
 
@@ -63,6 +73,10 @@ impl CopilotHarness {
 }
 
 impl AgentHarness for CopilotHarness {
+    fn name(&self) -> &str {
+        "copilot"
+    }
+
     fn run(&self, prompt: &str) -> anyhow::Result<String> {
         let output = self.build_command(prompt).output().context("run copilot")?;
         if !output.status.success() {
@@ -81,3 +95,170 @@ impl AgentHarness for CopilotHarness {
         Ok(stdout)
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentTranscript {
+    #[serde(default)]
+    pub harness: Option<String>,
+    pub prompt_hash: String,
+    pub prompt: String,
+    pub response: String,
+}
+
+pub struct ReplayHarness {
+    name: String,
+    transcripts: HashMap<String, AgentTranscript>,
+}
+
+impl ReplayHarness {
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let mut transcripts = HashMap::new();
+        let entries =
+            fs::read_dir(dir).with_context(|| format!("read replay fixtures {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("read replay fixture {}", path.display()))?;
+            let transcript: AgentTranscript = serde_json::from_str(&contents)
+                .with_context(|| format!("parse replay fixture {}", path.display()))?;
+            transcripts.insert(prompt_hash(&transcript.prompt), transcript);
+        }
+        let mut recorded: Vec<&str> = transcripts
+            .values()
+            .filter_map(|transcript| transcript.harness.as_deref())
+            .collect();
+        recorded.sort_unstable();
+        recorded.dedup();
+        let name = match recorded.as_slice() {
+            [single] => single.to_string(),
+            _ => "replay".to_string(),
+        };
+        Ok(Self { name, transcripts })
+    }
+}
+
+impl AgentHarness for ReplayHarness {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run(&self, prompt: &str) -> anyhow::Result<String> {
+        let hash = prompt_hash(prompt);
+        self.transcripts
+            .get(&hash)
+            .map(|transcript| transcript.response.clone())
+            .ok_or_else(|| anyhow::anyhow!("no replay fixture for prompt hash {hash}"))
+    }
+}
+
+pub struct RecordingHarness {
+    inner: Box<dyn AgentHarness>,
+    dir: PathBuf,
+}
+
+impl RecordingHarness {
+    pub fn new(inner: Box<dyn AgentHarness>, dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("create recording directory {}", dir.display()))?;
+        Ok(Self { inner, dir })
+    }
+}
+
+impl AgentHarness for RecordingHarness {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn run(&self, prompt: &str) -> anyhow::Result<String> {
+        let response = self.inner.run(prompt)?;
+        let transcript = AgentTranscript {
+            harness: Some(self.inner.name().to_string()),
+            prompt_hash: prompt_hash(prompt),
+            prompt: prompt.to_string(),
+            response: response.clone(),
+        };
+        let path = self.dir.join(format!("{}.json", transcript.prompt_hash));
+        let payload = serde_json::to_string_pretty(&transcript).context("serialize transcript")?;
+        fs::write(&path, payload)
+            .with_context(|| format!("write transcript {}", path.display()))?;
+        Ok(response)
+    }
+}
+
+pub struct LimitedHarness {
+    inner: Box<dyn AgentHarness>,
+    limiter: Arc<AgentLimiter>,
+}
+
+impl LimitedHarness {
+    pub fn new(inner: Box<dyn AgentHarness>, limiter: Arc<AgentLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+impl AgentHarness for LimitedHarness {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn run(&self, prompt: &str) -> anyhow::Result<String> {
+        self.limiter
+            .run(self.inner.name(), || self.inner.run(prompt))
+    }
+}
+
+pub fn normalize_prompt(prompt: &str) -> String {
+    prompt
+        .replace("\r\n", "\n")
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+pub fn prompt_hash(prompt: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(normalize_prompt(prompt).as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StaticHarness(&'static str);
+
+    impl AgentHarness for StaticHarness {
+        fn name(&self) -> &str {
+            "static"
+        }
+
+        fn run(&self, _prompt: &str) -> anyhow::Result<String> {
+            Ok(self.0.to_string())
+        }
+    }
+
+    #[test]
+    fn replays_recorded_transcripts_by_normalized_prompt() {
+        let dir = std::env::temp_dir().join(format!("hyperion-replay-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let recorder = RecordingHarness::new(Box::new(StaticHarness("{\"ok\":true}")), &dir)
+            .expect("create recorder");
+        recorder.run("Task ID: T-1\r\nFiles: a.rs  \n").unwrap();
+
+        let replay = ReplayHarness::load(&dir).expect("load fixtures");
+        assert_eq!(replay.name(), "static");
+        assert_eq!(
+            replay.run("Task ID: T-1\nFiles: a.rs").unwrap(),
+            "{\"ok\":true}"
+        );
+        assert!(replay.run("Task ID: T-2").is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,659 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::Context;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::events::{EventFeed, EVENT_BATCH_LIMIT, EVENT_POLL_INTERVAL};
+use crate::models::{CancelOutcome, ChangeRequest, QueueRecord, QueueStatus};
+use crate::queue::SqliteQueue;
+use crate::validator;
+
+const MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+const DEFAULT_LIST_LIMIT: usize = 100;
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const SSE_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Default)]
+pub struct ApiRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub body: Value,
+}
+
+pub struct ApiServer {
+    queue: Arc<SqliteQueue>,
+    token: Option<String>,
+}
+
+impl ApiRequest {
+    pub fn new(method: &str, target: &str) -> Self {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        Self {
+            method: method.to_ascii_uppercase(),
+            path: path.to_string(),
+            query: parse_query(query),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn query_value(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn query_parsed<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, ApiResponse> {
+        match self.query_value(name) {
+            Some(raw) => raw
+                .parse()
+                .map(Some)
+                .map_err(|_| ApiResponse::error(400, &format!("invalid `{name}` parameter"))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn read_from<R: Read>(stream: R) -> anyhow::Result<Self> {
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).context("read request line")?;
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            anyhow::bail!("malformed request line");
+        };
+        let mut request = Self::new(method, target);
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).context("read header")? == 0 {
+                break;
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                request = request.with_header(name.trim(), value.trim());
+            }
+        }
+        let length: usize = request
+            .header("content-length")
+            .map(str::parse)
+            .transpose()
+            .context("parse content-length")?
+            .unwrap_or(0);
+        if length > MAX_BODY_BYTES {
+            anyhow::bail!("request body of {length} bytes exceeds {MAX_BODY_BYTES}");
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).context("read request body")?;
+        Ok(request.with_body(body))
+    }
+}
+
+impl ApiResponse {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+
+    fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        match serde_json::to_value(value) {
+            Ok(body) => Self { status, body },
+            Err(err) => Self::error(500, &err.to_string()),
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, mut stream: W) -> std::io::Result<()> {
+        let body = self.body.to_string();
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason_phrase(self.status),
+            body.len(),
+            body
+        )?;
+        stream.flush()
+    }
+}
+
+impl ApiServer {
+    pub fn new(queue: Arc<SqliteQueue>, token: Option<String>) -> Self {
+        Self {
+            queue,
+            token: token.filter(|token| !token.is_empty()),
+        }
+    }
+
+    pub fn handle(&self, request: &ApiRequest) -> ApiResponse {
+        if request.path != "/health" && !self.authorized(request) {
+            return ApiResponse::error(401, "missing or invalid bearer token");
+        }
+        match self.route(request) {
+            Ok(response) | Err(response) => response,
+        }
+    }
+
+    fn authorized(&self, request: &ApiRequest) -> bool {
+        let Some(token) = self.token.as_deref() else {
+            return true;
+        };
+        request
+            .header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|presented| presented.trim() == token)
+    }
+
+    fn route(&self, request: &ApiRequest) -> Result<ApiResponse, ApiResponse> {
+        let segments: Vec<&str> = request
+            .path
+            .trim_matches('/')
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["health"]) => Ok(ApiResponse::ok(json!({ "status": "ok" }))),
+            ("POST", ["queue"]) => self.enqueue(request),
+            ("GET", ["queue"]) => self.list(request),
+            ("GET", ["queue", id]) => self.get(parse_id(id)?),
+            ("POST", ["queue", id, "approve"]) => self.approve(parse_id(id)?),
+            ("POST", ["queue", id, "requeue"]) => self.requeue(parse_id(id)?),
+            ("POST", ["queue", id, "cancel"]) => self.cancel(parse_id(id)?),
+            ("GET", ["dead-letters"]) => self.dead_letters(request),
+            ("GET", ["events"]) => {
+                let after = self.event_cursor(request)?.unwrap_or(0);
+                let limit = request.query_parsed("limit")?.unwrap_or(EVENT_BATCH_LIMIT);
+                let events = self.queue.events_after(after, limit).map_err(internal)?;
+                Ok(ApiResponse::json(200, &events))
+            }
+            ("GET", ["metrics"]) => {
+                let since = request.query_parsed("since")?;
+                Ok(ApiResponse::json(
+                    200,
+                    &self.queue.queue_metrics(since).map_err(internal)?,
+                ))
+            }
+            (_, ["health"] | ["queue", ..] | ["dead-letters"] | ["events"] | ["metrics"]) => {
+                Err(ApiResponse::error(405, "method not allowed"))
+            }
+            _ => Err(ApiResponse::error(404, "not found")),
+        }
+    }
+
+    fn enqueue(&self, request: &ApiRequest) -> Result<ApiResponse, ApiResponse> {
+        let change: ChangeRequest = serde_json::from_slice(&request.body)
+            .map_err(|err| ApiResponse::error(400, &format!("invalid change request: {err}")))?;
+        let validation = validator::validate_change_request(&change);
+        if !validation.valid {
+            return Err(ApiResponse {
+                status: 422,
+                body: json!({ "error": "validation failed", "errors": validation.errors }),
+            });
+        }
+        match self.queue.enqueue(&change) {
+            Ok(id) => Ok(ApiResponse {
+                status: 201,
+                body: json!({ "id": id, "task_id": change.task_id }),
+            }),
+            Err(err) if err.to_string().starts_with("duplicate change request") => {
+                Err(ApiResponse::error(409, &err.to_string()))
+            }
+            Err(err) => Err(internal(err)),
+        }
+    }
+
+    fn list(&self, request: &ApiRequest) -> Result<ApiResponse, ApiResponse> {
+        let status = request
+            .query_value("status")
+            .map(|raw| {
+                QueueStatus::from_string(raw)
+                    .map_err(|_| ApiResponse::error(400, &format!("unknown status `{raw}`")))
+            })
+            .transpose()?;
+        let since = request.query_parsed("since")?;
+        let limit = request.query_parsed("limit")?.unwrap_or(DEFAULT_LIST_LIMIT);
+        let records = self
+            .queue
+            .list_filtered(status, since, request.query_value("task_id"), limit)
+            .map_err(internal)?;
+        Ok(ApiResponse::json(200, &records))
+    }
+
+    fn get(&self, id: i64) -> Result<ApiResponse, ApiResponse> {
+        let record = self.find(id)?;
+        let logs = self.queue.logs_for_record(id).map_err(internal)?;
+        Ok(ApiResponse::ok(json!({ "record": record, "logs": logs })))
+    }
+
+    fn approve(&self, id: i64) -> Result<ApiResponse, ApiResponse> {
+        let record = self.find(id)?;
+        if record.status == QueueStatus::Applied {
+            return Err(ApiResponse::error(409, "record is already applied"));
+        }
+        self.queue.mark_applied(id).map_err(internal)?;
+        let _ = self.queue.log_event(
+            id,
+            &record.payload.task_id,
+            "info",
+            "approved via api",
+            None,
+        );
+        Ok(ApiResponse::json(200, &self.find(id)?))
+    }
+
+    fn requeue(&self, id: i64) -> Result<ApiResponse, ApiResponse> {
+        let record = self.find(id)?;
+        if !self.queue.requeue(id).map_err(internal)? {
+            return Err(ApiResponse::error(
+                409,
+                &format!(
+                    "only failed records can be requeued (status is {})",
+                    record.status.as_str()
+                ),
+            ));
+        }
+        let _ = self.queue.log_event(
+            id,
+            &record.payload.task_id,
+            "info",
+            "requeued via api",
+            None,
+        );
+        Ok(ApiResponse::json(200, &self.find(id)?))
+    }
+
+    fn cancel(&self, id: i64) -> Result<ApiResponse, ApiResponse> {
+        let outcome = self
+            .queue
+            .cancel(id)
+            .map_err(internal)?
+            .ok_or_else(|| ApiResponse::error(404, &format!("queue record {id} not found")))?;
+        if let CancelOutcome::Finished(status) = outcome {
+            return Err(ApiResponse::error(
+                409,
+                &format!("record already finished (status is {})", status.as_str()),
+            ));
+        }
+        Ok(ApiResponse::json(
+            200,
+            &json!({ "cancel": outcome, "record": self.find(id)? }),
+        ))
+    }
+
+    fn dead_letters(&self, request: &ApiRequest) -> Result<ApiResponse, ApiResponse> {
+        let since: Option<i64> = request.query_parsed("since")?;
+        let limit = request.query_parsed("limit")?.unwrap_or(DEFAULT_LIST_LIMIT);
+        let mut records = self.queue.list_dead_letters().map_err(internal)?;
+        if let Some(since) = since {
+            records.retain(|record| record.failed_at >= since);
+        }
+        records.truncate(limit);
+        Ok(ApiResponse::json(200, &records))
+    }
+
+    fn find(&self, id: i64) -> Result<QueueRecord, ApiResponse> {
+        self.queue
+            .get_record(id)
+            .map_err(internal)?
+            .ok_or_else(|| ApiResponse::error(404, &format!("queue record {id} not found")))
+    }
+
+    fn event_cursor(&self, request: &ApiRequest) -> Result<Option<i64>, ApiResponse> {
+        if let Some(since) = request.query_parsed("since")? {
+            return Ok(Some(since));
+        }
+        request
+            .header("last-event-id")
+            .map(|raw| {
+                raw.trim()
+                    .parse()
+                    .map_err(|_| ApiResponse::error(400, "invalid Last-Event-ID header"))
+            })
+            .transpose()
+    }
+
+    fn wants_event_stream(request: &ApiRequest) -> bool {
+        request.method == "GET"
+            && request.path.trim_end_matches('/') == "/events"
+            && request
+                .header("accept")
+                .is_some_and(|accept| accept.contains("text/event-stream"))
+    }
+
+    pub fn stream_events<W: Write>(
+        &self,
+        request: &ApiRequest,
+        mut stream: W,
+        running: &AtomicBool,
+    ) -> std::io::Result<()> {
+        let after = match self.event_cursor(request) {
+            Ok(after) => after,
+            Err(response) => return response.write_to(stream),
+        };
+        let mut feed = match after {
+            Some(seq) => EventFeed::after(&self.queue, seq),
+            None => match EventFeed::from_latest(&self.queue) {
+                Ok(feed) => feed,
+                Err(err) => return internal(err).write_to(stream),
+            },
+        };
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n: resuming after {}\n\n",
+            feed.cursor()
+        )?;
+        stream.flush()?;
+        let mut idle = Duration::ZERO;
+        while running.load(Ordering::SeqCst) {
+            let events = feed.next_batch().map_err(std::io::Error::other)?;
+            for event in &events {
+                let data = serde_json::to_string(event).map_err(std::io::Error::other)?;
+                write!(
+                    stream,
+                    "id: {}\nevent: {}\ndata: {data}\n\n",
+                    event.seq, event.kind
+                )?;
+            }
+            if events.is_empty() {
+                if idle >= SSE_HEARTBEAT_INTERVAL {
+                    stream.write_all(b": heartbeat\n\n")?;
+                    idle = Duration::ZERO;
+                }
+                stream.flush()?;
+                feed.wait();
+                idle += EVENT_POLL_INTERVAL;
+            } else {
+                stream.flush()?;
+                idle = Duration::ZERO;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_stream<S: Read + Write>(&self, mut stream: S, running: &AtomicBool) {
+        let response = match ApiRequest::read_from(&mut stream) {
+            Ok(request) if Self::wants_event_stream(&request) && self.authorized(&request) => {
+                let _ = self.stream_events(&request, &mut stream, running);
+                return;
+            }
+            Ok(request) => self.handle(&request),
+            Err(err) => ApiResponse::error(400, &err.to_string()),
+        };
+        let _ = response.write_to(&mut stream);
+    }
+
+    pub fn serve_tcp(
+        self: Arc<Self>,
+        listener: TcpListener,
+        running: Arc<AtomicBool>,
+    ) -> anyhow::Result<()> {
+        listener.set_nonblocking(true)?;
+        while running.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(false)?;
+                    let server = self.clone();
+                    let running = running.clone();
+                    thread::spawn(move || server.handle_stream(stream, &running));
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                    thread::sleep(ACCEPT_POLL_INTERVAL);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    pub fn serve_unix(
+        self: Arc<Self>,
+        listener: std::os::unix::net::UnixListener,
+        running: Arc<AtomicBool>,
+    ) -> anyhow::Result<()> {
+        listener.set_nonblocking(true)?;
+        while running.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(false)?;
+                    let server = self.clone();
+                    let running = running.clone();
+                    thread::spawn(move || server.handle_stream(stream, &running));
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                    thread::sleep(ACCEPT_POLL_INTERVAL);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn bind_local(addr: &str) -> anyhow::Result<TcpListener> {
+    let addr: SocketAddr = addr
+        .parse()
+        .with_context(|| format!("parse listen address {addr}"))?;
+    if !addr.ip().is_loopback() {
+        anyhow::bail!(
+            "refusing to bind {addr}: the API only listens on loopback addresses or a unix socket"
+        );
+    }
+    TcpListener::bind(addr).with_context(|| format!("bind {addr}"))
+}
+
+fn parse_id(raw: &str) -> Result<i64, ApiResponse> {
+    raw.parse()
+        .map_err(|_| ApiResponse::error(400, &format!("invalid queue id `{raw}`")))
+}
+
+fn internal(err: anyhow::Error) -> ApiResponse {
+    ApiResponse::error(500, &err.to_string())
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'+' => decoded.push(b' '),
+            b'%' if index + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[index + 1..index + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        decoded.push(byte);
+                        index += 2;
+                    }
+                    Err(_) => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        422 => "Unprocessable Entity",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpStream;
+
+    fn change(task_id: &str) -> String {
+        let patch = "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-a\n+b\n";
+        json!({
+            "task_id": task_id,
+            "agent": "agent-1",
+            "changes": [{
+                "path": "src/lib.rs",
+                "operation": "update",
+                "patch": patch,
+                "patch_hash": crate::context::sha256_hex(patch.as_bytes())
+            }],
+            "checks": ["cargo test"]
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn serves_queue_operations_in_process_and_over_tcp() {
+        let db = std::env::temp_dir().join(format!("hyperion-api-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&db);
+        let queue = Arc::new(SqliteQueue::open(&db).unwrap());
+        let server = Arc::new(ApiServer::new(queue.clone(), Some("secret".to_string())));
+        let authed = |method: &str, target: &str| {
+            ApiRequest::new(method, target).with_header("Authorization", "Bearer secret")
+        };
+
+        assert_eq!(server.handle(&ApiRequest::new("GET", "/queue")).status, 401);
+        assert_eq!(
+            server.handle(&ApiRequest::new("GET", "/health")).status,
+            200
+        );
+
+        let invalid = server.handle(&authed("POST", "/queue").with_body(change("")));
+        assert_eq!(invalid.status, 422);
+        assert!(invalid.body["errors"][0]
+            .as_str()
+            .unwrap()
+            .contains("task_id"));
+
+        let created = server.handle(&authed("POST", "/queue").with_body(change("API-1")));
+        assert_eq!(created.status, 201);
+        let id = created.body["id"].as_i64().unwrap();
+        let duplicate = server.handle(&authed("POST", "/queue").with_body(change("API-1")));
+        assert_eq!(duplicate.status, 409);
+
+        let listed = server.handle(&authed("GET", "/queue?status=pending&task_id=API-1"));
+        assert_eq!(listed.body.as_array().unwrap().len(), 1);
+
+        queue.mark_failed(id, Some("boom".to_string())).unwrap();
+        let requeued = server.handle(&authed("POST", &format!("/queue/{id}/requeue")));
+        assert_eq!(requeued.status, 200);
+        assert_eq!(requeued.body["status"], "pending");
+        assert!(queue.list_dead_letters().unwrap().is_empty());
+
+        let approved = server.handle(&authed("POST", &format!("/queue/{id}/approve")));
+        assert_eq!(approved.body["status"], "applied");
+        let detail = server.handle(&authed("GET", &format!("/queue/{id}")));
+        let messages: Vec<&str> = detail.body["logs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|log| log["message"].as_str())
+            .collect();
+        assert!(messages.contains(&"requeued via api"));
+        assert!(messages.contains(&"approved via api"));
+        assert_eq!(server.handle(&authed("GET", "/queue/999")).status, 404);
+
+        let listener = bind_local("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let running = Arc::new(AtomicBool::new(true));
+        let handle = {
+            let server = server.clone();
+            let running = running.clone();
+            thread::spawn(move || server.serve_tcp(listener, running))
+        };
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "GET /metrics HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer secret\r\n\r\n"
+        )
+        .unwrap();
+        let mut raw = String::new();
+        stream.read_to_string(&mut raw).unwrap();
+        assert!(raw.starts_with("HTTP/1.1 200 OK"));
+        assert!(raw.contains("\"status_counts\""));
+        let mut events = TcpStream::connect(addr).unwrap();
+        write!(
+            events,
+            "GET /events HTTP/1.1\r\nAccept: text/event-stream\r\nAuthorization: Bearer secret\r\nLast-Event-ID: 1\r\n\r\n"
+        )
+        .unwrap();
+        let mut ids = Vec::new();
+        for line in BufReader::new(events).lines() {
+            let line = line.unwrap();
+            if let Some(id) = line.strip_prefix("id: ") {
+                ids.push(id.parse::<i64>().unwrap());
+            }
+            if ids.len() == 3 {
+                break;
+            }
+        }
+        assert_eq!(ids, vec![2, 3, 4]);
+        let batch = server.handle(&authed("GET", "/events?since=2&limit=1"));
+        assert_eq!(batch.body[0]["seq"], 3);
+
+        running.store(false, Ordering::SeqCst);
+        handle.join().unwrap().unwrap();
+        assert!(bind_local("0.0.0.0:0").is_err());
+        let _ = std::fs::remove_file(&db);
+    }
+}
@@ -9,8 +9,10 @@ use std::{
 
 use anyhow::{Context, Error};
 use diffy::{apply, Patch};
+use serde::Serialize;
 use tracing::info;
 
+use crate::context;
 use crate::models::{ChangeOperation, ChangeRequest, OperationKind};
 
 #[derive(Debug)]
@@ -47,6 +49,42 @@ impl StdError for ApplyFailure {
     }
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct ApplyCheck {
+    pub path: String,
+    pub ok: bool,
+    pub error: Option<String>,
+    pub stderr: String,
+}
+
+pub fn check_change_request(request: &ChangeRequest) -> Vec<ApplyCheck> {
+    request
+        .changes
+        .iter()
+        .map(|change| {
+            let result = run_git_apply_check(change)
+                .and_then(|_| verify_base_hash(Path::new(&change.path), change));
+            match result {
+                Ok(()) => ApplyCheck {
+                    path: change.path.clone(),
+                    ok: true,
+                    error: None,
+                    stderr: String::new(),
+                },
+                Err(err) => ApplyCheck {
+                    path: change.path.clone(),
+                    ok: false,
+                    stderr: err
+                        .downcast_ref::<ApplyFailure>()
+                        .map(|failure| failure.stderr.clone())
+                        .unwrap_or_default(),
+                    error: Some(err.to_string()),
+                },
+            }
+        })
+        .collect()
+}
+
 pub fn apply_change_request(request: &ChangeRequest) -> anyhow::Result<()> {
     info!(
         task_id = %request.task_id,
@@ -78,6 +116,7 @@ pub fn apply_change_request(request: &ChangeRequest) -> anyhow::Result<()> {
 fn apply_change_operation(change: ChangeOperation) -> anyhow::Result<()> {
     run_git_apply_check(&change)?;
     let target = Path::new(&change.path);
+    verify_base_hash(target, &change)?;
     info!(
         path = %change.path,
         operation = ?change.operation,
@@ -156,6 +195,26 @@ fn run_git_apply_check(change: &ChangeOperation) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn verify_base_hash(target: &Path, change: &ChangeOperation) -> anyhow::Result<()> {
+    let Some(expected) = change.base_hash.as_ref() else {
+        return Ok(());
+    };
+    let current = fs::read(target).unwrap_or_default();
+    let actual = context::sha256_hex(&current);
+    if &actual != expected {
+        return Err(Error::from(ApplyFailure::new(
+            anyhow::anyhow!(
+                "base_hash mismatch for {} (expected {expected}, found {actual})",
+                change.path
+            ),
+            change.patch.clone(),
+            String::new(),
+            String::new(),
+        )));
+    }
+    Ok(())
+}
+
 fn write_modification(target: &Path, base: &str, change: &ChangeOperation) -> anyhow::Result<()> {
     if let Some(parent) = target.parent() {
         fs::create_dir_all(parent).context("create target directories")?;
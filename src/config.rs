@@ -0,0 +1,585 @@
+use std::{
+    collections::BTreeMap,
+    env, fmt, fs,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+use crate::limits::DEFAULT_AGENT_CONCURRENCY;
+use crate::models::DEFAULT_QUEUE;
+use crate::prompt::DEFAULT_PROMPT_DIR;
+use crate::queue::{
+    validate_queue_name, DEFAULT_APPLIED_RETENTION_SECS, DEFAULT_DEADLETTER_RETENTION_SECS,
+};
+use crate::request::DEFAULT_MODEL;
+
+pub const REPO_CONFIG_FILE: &str = "hyperion.toml";
+const ENV_PREFIX: &str = "HYPERION_";
+const QUEUES_SECTION: &str = "queues";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HyperionConfig {
+    pub queue: QueueSection,
+    pub worker: WorkerSection,
+    pub agents: AgentSection,
+    pub checks: CheckSection,
+    pub retention: RetentionSection,
+    pub tui: TuiSection,
+    pub api: ApiSection,
+    pub daemon: DaemonSection,
+    pub queues: BTreeMap<String, QueueOverrides>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueueSection {
+    pub db: PathBuf,
+    pub name: String,
+    pub lease_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkerSection {
+    pub count: usize,
+    pub max_workers: usize,
+    pub poll_interval_ms: u64,
+    pub max_attempts: i64,
+    pub network: bool,
+    pub worktree: String,
+    pub labels: Vec<String>,
+    pub queues: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentSection {
+    pub harness: String,
+    pub model: String,
+    pub count: usize,
+    pub max_concurrency: usize,
+    pub rate_limit_per_minute: u32,
+    pub prompts_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CheckSection {
+    pub enabled: bool,
+    pub allowed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionSection {
+    pub applied_secs: i64,
+    pub dead_letter_secs: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TuiSection {
+    pub refresh_intervals_ms: Vec<u64>,
+    pub console_log: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiSection {
+    pub addr: String,
+    pub socket: String,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueueOverrides {
+    pub workers: Option<usize>,
+    pub max_in_flight: Option<usize>,
+    pub applied_secs: Option<i64>,
+    pub dead_letter_secs: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaemonSection {
+    pub pidfile: PathBuf,
+    pub inbox: String,
+}
+
+impl Default for QueueSection {
+    fn default() -> Self {
+        Self {
+            db: PathBuf::from("hyperion.db"),
+            name: DEFAULT_QUEUE.to_string(),
+            lease_seconds: 300,
+        }
+    }
+}
+
+impl Default for WorkerSection {
+    fn default() -> Self {
+        Self {
+            count: 3,
+            max_workers: thread::available_parallelism()
+                .map(|count| count.get())
+                .unwrap_or(3)
+                .max(3),
+            poll_interval_ms: 500,
+            max_attempts: 5,
+            network: true,
+            worktree: "shared".to_string(),
+            labels: Vec::new(),
+            queues: Vec::new(),
+        }
+    }
+}
+
+impl Default for AgentSection {
+    fn default() -> Self {
+        Self {
+            harness: "stub".to_string(),
+            model: DEFAULT_MODEL.to_string(),
+            count: 3,
+            max_concurrency: DEFAULT_AGENT_CONCURRENCY,
+            rate_limit_per_minute: 0,
+            prompts_dir: PathBuf::from(DEFAULT_PROMPT_DIR),
+        }
+    }
+}
+
+impl Default for CheckSection {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            allowed: vec![
+                "cargo test".to_string(),
+                "cargo check".to_string(),
+                "cargo clippy".to_string(),
+                "cargo fmt".to_string(),
+            ],
+        }
+    }
+}
+
+impl Default for RetentionSection {
+    fn default() -> Self {
+        Self {
+            applied_secs: DEFAULT_APPLIED_RETENTION_SECS,
+            dead_letter_secs: DEFAULT_DEADLETTER_RETENTION_SECS,
+        }
+    }
+}
+
+impl Default for TuiSection {
+    fn default() -> Self {
+        Self {
+            refresh_intervals_ms: vec![200, 500, 1000, 2000],
+            console_log: false,
+        }
+    }
+}
+
+impl Default for ApiSection {
+    fn default() -> Self {
+        Self {
+            addr: "127.0.0.1:7420".to_string(),
+            socket: String::new(),
+            token: String::new(),
+        }
+    }
+}
+
+impl Default for DaemonSection {
+    fn default() -> Self {
+        Self {
+            pidfile: PathBuf::from("hyperion.pid"),
+            inbox: String::new(),
+        }
+    }
+}
+
+impl HyperionConfig {
+    pub fn queue_overrides(&self) -> QueueOverrides {
+        self.queues
+            .get(&self.queue.name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn worker_count(&self) -> usize {
+        self.queue_overrides().workers.unwrap_or(self.worker.count)
+    }
+
+    pub fn queue_retention(&self) -> RetentionSection {
+        let overrides = self.queue_overrides();
+        RetentionSection {
+            applied_secs: overrides
+                .applied_secs
+                .unwrap_or(self.retention.applied_secs),
+            dead_letter_secs: overrides
+                .dead_letter_secs
+                .unwrap_or(self.retention.dead_letter_secs),
+        }
+    }
+
+    pub fn subscriptions(&self) -> Vec<String> {
+        if self.worker.queues.is_empty() {
+            vec![self.queue.name.clone()]
+        } else {
+            self.worker.queues.clone()
+        }
+    }
+
+    pub fn max_in_flight(&self) -> BTreeMap<String, usize> {
+        self.queues
+            .iter()
+            .filter_map(|(name, overrides)| Some((name.clone(), overrides.max_in_flight?)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    RepoFile(PathBuf),
+    UserFile(PathBuf),
+    Env(String),
+    Flag(String),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::RepoFile(path) => write!(f, "repo config {}", path.display()),
+            ConfigSource::UserFile(path) => write!(f, "user config {}", path.display()),
+            ConfigSource::Env(name) => write!(f, "env {name}"),
+            ConfigSource::Flag(flag) => write!(f, "flag {flag}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CliOverrides {
+    entries: Vec<(&'static str, Value, &'static str)>,
+}
+
+impl CliOverrides {
+    pub fn set(&mut self, key: &'static str, flag: &'static str, value: impl Into<Value>) {
+        self.entries.push((key, value.into(), flag));
+    }
+
+    pub fn set_opt<T: Into<Value>>(
+        &mut self,
+        key: &'static str,
+        flag: &'static str,
+        value: Option<T>,
+    ) {
+        if let Some(value) = value {
+            self.set(key, flag, value);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectiveSetting {
+    pub key: String,
+    pub value: String,
+    pub source: String,
+}
+
+#[derive(Debug)]
+pub struct LoadedConfig {
+    pub config: HyperionConfig,
+    merged: Table,
+    sources: BTreeMap<String, ConfigSource>,
+}
+
+impl LoadedConfig {
+    pub fn load(repo_file: Option<&Path>, overrides: &CliOverrides) -> anyhow::Result<Self> {
+        let env_vars: Vec<(String, String)> = env::vars().collect();
+        let repo_file = repo_file
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from(REPO_CONFIG_FILE));
+        Self::from_layers(
+            &repo_file,
+            user_config_path().as_deref(),
+            &env_vars,
+            overrides,
+        )
+    }
+
+    fn from_layers(
+        repo_file: &Path,
+        user_file: Option<&Path>,
+        env_vars: &[(String, String)],
+        overrides: &CliOverrides,
+    ) -> anyhow::Result<Self> {
+        let defaults = Value::try_from(HyperionConfig::default())
+            .context("serialize default configuration")?;
+        let Value::Table(merged) = defaults else {
+            anyhow::bail!("default configuration is not a table");
+        };
+        let mut loaded = Self {
+            config: HyperionConfig::default(),
+            sources: leaf_keys(&merged)
+                .into_iter()
+                .map(|key| (key, ConfigSource::Default))
+                .collect(),
+            merged,
+        };
+
+        if repo_file.is_file() {
+            let layer = read_table(repo_file)?;
+            loaded.merge(layer, ConfigSource::RepoFile(repo_file.to_path_buf()))?;
+        }
+        if let Some(user_file) = user_file.filter(|path| path.is_file()) {
+            let layer = read_table(user_file)?;
+            loaded.merge(layer, ConfigSource::UserFile(user_file.to_path_buf()))?;
+        }
+        for (name, raw) in env_vars {
+            let Some(key) = env_key(name, &loaded.sources) else {
+                continue;
+            };
+            let value = if name == "HYPERION_LOG" {
+                Value::Boolean(true)
+            } else {
+                parse_env_value(&loaded.merged, &key, raw)
+                    .with_context(|| format!("parse environment variable {name}"))?
+            };
+            loaded.set(&key, value, ConfigSource::Env(name.clone()))?;
+        }
+        for (key, value, flag) in &overrides.entries {
+            loaded.set(key, value.clone(), ConfigSource::Flag(flag.to_string()))?;
+        }
+
+        loaded.config = HyperionConfig::deserialize(Value::Table(loaded.merged.clone()))
+            .context("invalid hyperion configuration")?;
+        Ok(loaded)
+    }
+
+    fn merge(&mut self, layer: Table, source: ConfigSource) -> anyhow::Result<()> {
+        for (section, values) in layer {
+            let Value::Table(values) = values else {
+                anyhow::bail!("{source}: `{section}` must be a table");
+            };
+            for (name, value) in values {
+                self.set(&format!("{section}.{name}"), value, source.clone())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, key: &str, value: Value, source: ConfigSource) -> anyhow::Result<()> {
+        let (section, name) = key.split_once('.').unwrap_or((key, ""));
+        let per_queue = section == QUEUES_SECTION;
+        if per_queue {
+            validate_queue_name(name).with_context(|| format!("{source}: `{key}`"))?;
+            if !matches!(value, Value::Table(_)) {
+                anyhow::bail!("{source}: `{key}` must be a table");
+            }
+        } else if !self.sources.contains_key(key) {
+            anyhow::bail!("{source}: unknown setting `{key}`");
+        }
+        if let Some(Value::Table(table)) = self.merged.get_mut(section) {
+            match (table.get_mut(name), value) {
+                (Some(Value::Table(existing)), Value::Table(layer)) if per_queue => {
+                    existing.extend(layer)
+                }
+                (_, value) => {
+                    table.insert(name.to_string(), value);
+                }
+            }
+        }
+        self.sources.insert(key.to_string(), source);
+        Ok(())
+    }
+
+    pub fn effective(&self) -> Vec<EffectiveSetting> {
+        self.sources
+            .iter()
+            .map(|(key, source)| {
+                let (section, name) = key.split_once('.').unwrap_or((key, ""));
+                let value = self
+                    .merged
+                    .get(section)
+                    .and_then(|table| table.get(name))
+                    .map(|value| match value {
+                        Value::String(secret) if name == "token" && !secret.is_empty() => {
+                            "\"<redacted>\"".to_string()
+                        }
+                        value => value.to_string(),
+                    })
+                    .unwrap_or_default();
+                EffectiveSetting {
+                    key: key.clone(),
+                    value,
+                    source: source.to_string(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        let mut current_section = String::new();
+        for setting in self.effective() {
+            let (section, name) = setting
+                .key
+                .split_once('.')
+                .unwrap_or((setting.key.as_str(), ""));
+            if section != current_section {
+                if !output.is_empty() {
+                    output.push('\n');
+                }
+                output.push_str(&format!("[{section}]\n"));
+                current_section = section.to_string();
+            }
+            output.push_str(&format!(
+                "{name} = {}  # {}\n",
+                setting.value, setting.source
+            ));
+        }
+        output
+    }
+}
+
+pub fn user_config_path() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir).join("hyperion").join("config.toml"));
+    }
+    env::var_os("HOME").map(|home| {
+        PathBuf::from(home)
+            .join(".config")
+            .join("hyperion")
+            .join("config.toml")
+    })
+}
+
+fn read_table(path: &Path) -> anyhow::Result<Table> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("read config {}", path.display()))?;
+    contents
+        .parse::<Table>()
+        .with_context(|| format!("parse config {}", path.display()))
+}
+
+fn leaf_keys(table: &Table) -> Vec<String> {
+    let mut keys = Vec::new();
+    for (section, values) in table {
+        if let Value::Table(values) = values {
+            for name in values.keys() {
+                keys.push(format!("{section}.{name}"));
+            }
+        }
+    }
+    keys
+}
+
+fn env_key(name: &str, known: &BTreeMap<String, ConfigSource>) -> Option<String> {
+    match name {
+        "HYPERION_AGENT" => return Some("agents.harness".to_string()),
+        "HYPERION_LOG" => return Some("tui.console_log".to_string()),
+        _ => {}
+    }
+    let suffix = name.strip_prefix(ENV_PREFIX)?;
+    known
+        .keys()
+        .find(|key| key.replace('.', "_").to_ascii_uppercase() == suffix)
+        .cloned()
+}
+
+fn parse_env_value(merged: &Table, key: &str, raw: &str) -> anyhow::Result<Value> {
+    let (section, name) = key.split_once('.').unwrap_or((key, ""));
+    let current = merged.get(section).and_then(|table| table.get(name));
+    let value = match current {
+        Some(Value::Integer(_)) => Value::Integer(raw.trim().parse()?),
+        Some(Value::Boolean(_)) => Value::Boolean(match raw.trim() {
+            "1" | "true" | "yes" | "on" => true,
+            "0" | "false" | "no" | "off" => false,
+            other => anyhow::bail!("expected a boolean, got `{other}`"),
+        }),
+        Some(Value::Array(_)) => format!("value = {raw}")
+            .parse::<Table>()?
+            .remove("value")
+            .unwrap_or(Value::Array(Vec::new())),
+        _ => Value::String(raw.to_string()),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_layers_override_earlier_ones_and_record_sources() {
+        let root = std::env::temp_dir().join(format!("hyperion-config-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let repo_file = root.join("hyperion.toml");
+        let user_file = root.join("user.toml");
+        fs::write(
+            &repo_file,
+            "[worker]\ncount = 8\npoll_interval_ms = 250\n\n[agents]\nmodel = \"repo-model\"\n\n[queues.team-a]\nworkers = 2\nmax_in_flight = 1\n",
+        )
+        .unwrap();
+        fs::write(
+            &user_file,
+            "[worker]\ncount = 4\n\n[queues.team-a]\napplied_secs = 60\n",
+        )
+        .unwrap();
+        let env_vars = vec![
+            ("HYPERION_AGENT".to_string(), "copilot".to_string()),
+            (
+                "HYPERION_WORKER_POLL_INTERVAL_MS".to_string(),
+                "100".to_string(),
+            ),
+            (
+                "HYPERION_TUI_REFRESH_INTERVALS_MS".to_string(),
+                "[100, 300]".to_string(),
+            ),
+        ];
+        let mut overrides = CliOverrides::default();
+        overrides.set("agents.model", "--model", "cli-model");
+        overrides.set("queue.name", "--queue", "team-a");
+
+        let loaded =
+            LoadedConfig::from_layers(&repo_file, Some(&user_file), &env_vars, &overrides).unwrap();
+        let config = &loaded.config;
+        assert_eq!(config.worker.count, 4);
+        assert_eq!(config.worker.poll_interval_ms, 100);
+        assert_eq!(config.worker.max_attempts, 5);
+        assert_eq!(config.agents.harness, "copilot");
+        assert_eq!(config.agents.model, "cli-model");
+        assert_eq!(config.tui.refresh_intervals_ms, vec![100, 300]);
+        assert_eq!(config.worker_count(), 2);
+        assert_eq!(config.queue_retention().applied_secs, 60);
+        assert_eq!(config.subscriptions(), vec!["team-a".to_string()]);
+        assert_eq!(config.max_in_flight().get("team-a"), Some(&1));
+        assert_eq!(
+            loaded.sources.get("worker.count"),
+            Some(&ConfigSource::UserFile(user_file.clone()))
+        );
+        assert_eq!(
+            loaded.sources.get("agents.harness"),
+            Some(&ConfigSource::Env("HYPERION_AGENT".to_string()))
+        );
+        assert_eq!(loaded.sources.get("queue.db"), Some(&ConfigSource::Default));
+        assert!(loaded
+            .render()
+            .contains("model = \"cli-model\"  # flag --model"));
+
+        fs::write(&repo_file, "[worker]\nthreads = 2\n").unwrap();
+        let err =
+            LoadedConfig::from_layers(&repo_file, None, &[], &CliOverrides::default()).unwrap_err();
+        assert!(err.to_string().contains("unknown setting `worker.threads`"));
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,377 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::models::TaskAssignment;
+
+pub const DEFAULT_CONTEXT_TOKENS: usize = 6000;
+pub const DEFAULT_CHUNK_LINES: usize = 200;
+const MAX_IMPORTERS: usize = 5;
+const MAX_SCAN_BYTES: u64 = 256 * 1024;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextRole {
+    Target,
+    Parent,
+    Importer,
+}
+
+impl ContextRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContextRole::Target => "target",
+            ContextRole::Parent => "module parent",
+            ContextRole::Importer => "imports target",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextChunk {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextFile {
+    pub path: String,
+    pub role: ContextRole,
+    pub exists: bool,
+    pub sha256: Option<String>,
+    pub total_lines: usize,
+    pub chunks: Vec<ContextChunk>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepositoryContext {
+    pub git_head: Option<String>,
+    pub token_budget: usize,
+    pub tokens_used: usize,
+    pub files: Vec<ContextFile>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContextAssembler {
+    pub root: PathBuf,
+    pub max_tokens: usize,
+    pub chunk_lines: usize,
+}
+
+impl ContextAssembler {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            max_tokens: DEFAULT_CONTEXT_TOKENS,
+            chunk_lines: DEFAULT_CHUNK_LINES,
+        }
+    }
+
+    pub fn assemble(&self, assignment: &TaskAssignment) -> RepositoryContext {
+        let mut remaining = self.max_tokens;
+        let mut files = Vec::new();
+        let target_budget = self.max_tokens * 3 / 4;
+        let per_target = target_budget / assignment.file_targets.len().max(1);
+        for target in &assignment.file_targets {
+            let budget = per_target.min(remaining);
+            let file = self.load(target, ContextRole::Target, budget);
+            remaining -= file_tokens(&file);
+            files.push(file);
+        }
+
+        let mut related = Vec::new();
+        for target in &assignment.file_targets {
+            for parent in module_parents(&self.root, target) {
+                related.push((parent, ContextRole::Parent));
+            }
+        }
+        let tracked = self.tracked_files();
+        for target in &assignment.file_targets {
+            for importer in importers(&self.root, &tracked, target) {
+                related.push((importer, ContextRole::Importer));
+            }
+        }
+        for (path, role) in related {
+            if remaining == 0 {
+                break;
+            }
+            if files.iter().any(|file| file.path == path) {
+                continue;
+            }
+            let file = self.load(&path, role, remaining);
+            remaining -= file_tokens(&file);
+            files.push(file);
+        }
+
+        RepositoryContext {
+            git_head: git_head(&self.root),
+            token_budget: self.max_tokens,
+            tokens_used: self.max_tokens - remaining,
+            files,
+        }
+    }
+
+    fn load(&self, path: &str, role: ContextRole, budget: usize) -> ContextFile {
+        let contents = match fs::read_to_string(self.root.join(path)) {
+            Ok(contents) => contents,
+            Err(_) => {
+                return ContextFile {
+                    path: path.to_string(),
+                    role,
+                    exists: false,
+                    sha256: None,
+                    total_lines: 0,
+                    chunks: Vec::new(),
+                    truncated: false,
+                }
+            }
+        };
+        let lines: Vec<&str> = contents.lines().collect();
+        let mut chunks = Vec::new();
+        let mut used = 0;
+        let mut truncated = false;
+        for (index, window) in lines.chunks(self.chunk_lines.max(1)).enumerate() {
+            let text = window.join("\n");
+            let cost = estimate_tokens(&text);
+            if used + cost > budget {
+                truncated = true;
+                break;
+            }
+            used += cost;
+            let start_line = index * self.chunk_lines.max(1) + 1;
+            chunks.push(ContextChunk {
+                start_line,
+                end_line: start_line + window.len() - 1,
+                text,
+            });
+        }
+        ContextFile {
+            path: path.to_string(),
+            role,
+            exists: true,
+            sha256: Some(sha256_hex(contents.as_bytes())),
+            total_lines: lines.len(),
+            chunks,
+            truncated,
+        }
+    }
+
+    fn tracked_files(&self) -> Vec<String> {
+        let output = Command::new("git")
+            .arg("ls-files")
+            .current_dir(&self.root)
+            .output();
+        match output {
+            Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(str::to_string)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl RepositoryContext {
+    pub fn render_files(&self) -> String {
+        let mut sections = Vec::new();
+        for file in &self.files {
+            let mut section = match &file.sha256 {
+                Some(hash) => format!(
+                    "### {} ({}, {} lines, sha256 {})",
+                    file.path,
+                    file.role.as_str(),
+                    file.total_lines,
+                    hash
+                ),
+                None => format!(
+                    "### {} ({}, file does not exist yet)",
+                    file.path,
+                    file.role.as_str()
+                ),
+            };
+            let chunked = file.chunks.len() > 1 || file.truncated;
+            for chunk in &file.chunks {
+                if chunked {
+                    section.push_str(&format!("\nlines {}-{}:", chunk.start_line, chunk.end_line));
+                }
+                section.push_str(&format!("\n```\n{}\n```", chunk.text));
+            }
+            if file.truncated {
+                let shown = file.chunks.last().map_or(0, |chunk| chunk.end_line);
+                section.push_str(&format!(
+                    "\n[truncated: lines {}-{} omitted to fit the context budget]",
+                    shown + 1,
+                    file.total_lines
+                ));
+            }
+            sections.push(section);
+        }
+        sections.join("\n\n")
+    }
+}
+
+fn module_parents(root: &Path, target: &str) -> Vec<String> {
+    let path = Path::new(target);
+    let mut candidates = Vec::new();
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("rs") => {
+            let stem = path.file_stem().and_then(|stem| stem.to_str());
+            let parent = path.parent().unwrap_or(Path::new(""));
+            if matches!(stem, Some("mod" | "lib" | "main")) {
+                if let Some(grandparent) = parent.parent() {
+                    if let Some(dir) = parent.file_name().and_then(|name| name.to_str()) {
+                        candidates.push(grandparent.join(format!("{dir}.rs")));
+                        candidates.push(grandparent.join("mod.rs"));
+                    }
+                }
+            } else {
+                for name in ["mod.rs", "lib.rs", "main.rs"] {
+                    candidates.push(parent.join(name));
+                }
+                if let (Some(grandparent), Some(dir)) = (
+                    parent.parent(),
+                    parent.file_name().and_then(|name| name.to_str()),
+                ) {
+                    candidates.push(grandparent.join(format!("{dir}.rs")));
+                }
+            }
+        }
+        Some("py") => candidates.push(path.with_file_name("__init__.py")),
+        Some("ts" | "tsx" | "js" | "jsx") => {
+            candidates.push(path.with_file_name("index.ts"));
+            candidates.push(path.with_file_name("index.js"));
+        }
+        _ => {}
+    }
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate != path && root.join(candidate).is_file())
+        .filter_map(|candidate| candidate.to_str().map(str::to_string))
+        .collect()
+}
+
+fn importers(root: &Path, tracked: &[String], target: &str) -> Vec<String> {
+    let Some(stem) = Path::new(target).file_stem().and_then(|stem| stem.to_str()) else {
+        return Vec::new();
+    };
+    if matches!(stem, "mod" | "lib" | "main" | "index" | "__init__") {
+        return Vec::new();
+    }
+    let mut found = Vec::new();
+    for path in tracked {
+        if found.len() >= MAX_IMPORTERS {
+            break;
+        }
+        if path == target {
+            continue;
+        }
+        let full = root.join(path);
+        if fs::metadata(&full).map_or(true, |meta| meta.len() > MAX_SCAN_BYTES) {
+            continue;
+        }
+        let Ok(contents) = fs::read_to_string(&full) else {
+            continue;
+        };
+        if contents.lines().any(|line| imports_stem(line.trim(), stem)) {
+            found.push(path.clone());
+        }
+    }
+    found
+}
+
+fn imports_stem(line: &str, stem: &str) -> bool {
+    let is_import = ["use ", "mod ", "pub mod ", "import ", "from ", "#include"]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+        || line.contains("require(");
+    is_import
+        && line
+            .split(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+            .any(|word| word == stem)
+}
+
+fn git_head(root: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("rev-parse")
+        .arg("HEAD")
+        .current_dir(root)
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
+    }
+}
+
+fn file_tokens(file: &ContextFile) -> usize {
+    file.chunks
+        .iter()
+        .map(|chunk| estimate_tokens(&chunk.text))
+        .sum()
+}
+
+fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_targets_to_budget_and_includes_module_parent() {
+        let root = std::env::temp_dir().join(format!("hyperion-context-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src/queue")).unwrap();
+        fs::write(root.join("src/queue.rs"), "pub mod store;\n").unwrap();
+        let body: String = (0..40).map(|n| format!("line {n:02} of store\n")).collect();
+        fs::write(root.join("src/queue/store.rs"), &body).unwrap();
+
+        let assembler = ContextAssembler {
+            root: root.clone(),
+            max_tokens: 80,
+            chunk_lines: 10,
+        };
+        let assignment = TaskAssignment {
+            task_id: "REQ-1-1".to_string(),
+            parent_request_id: "REQ-1".to_string(),
+            summary: "Touch the store".to_string(),
+            file_targets: vec!["src/queue/store.rs".to_string()],
+            instructions: Vec::new(),
+        };
+        let context = assembler.assemble(&assignment);
+
+        let target = &context.files[0];
+        assert_eq!(target.role, ContextRole::Target);
+        assert_eq!(target.total_lines, 40);
+        assert!(target.truncated);
+        assert_eq!(target.chunks.len(), 1);
+        assert_eq!(
+            target.sha256.as_deref(),
+            Some(sha256_hex(body.as_bytes()).as_str())
+        );
+        assert!(context.tokens_used <= 80);
+        assert!(context
+            .files
+            .iter()
+            .any(|file| file.path == "src/queue.rs" && file.role == ContextRole::Parent));
+        assert!(context
+            .render_files()
+            .contains("[truncated: lines 11-40 omitted to fit the context budget]"));
+        let _ = fs::remove_dir_all(&root);
+    }
+}
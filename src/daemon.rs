@@ -0,0 +1,343 @@
+use std::{
+    collections::VecDeque,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config::HyperionConfig;
+use crate::fs_watch;
+use crate::pool::{self, WorkerPool, WorkerSettings};
+use crate::queue::{SqliteQueue, CONTROL_DESIRED_WORKERS};
+use crate::watcher;
+use crate::worker;
+
+pub const CONTROL_DAEMON_STATUS: &str = "daemon_status";
+const STATUS_INTERVAL: Duration = Duration::from_secs(2);
+const STALE_AFTER_SECS: i64 = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub state: String,
+    pub started_at: i64,
+    pub heartbeat_at: i64,
+    pub workers: usize,
+    pub max_workers: usize,
+    pub worker_restarts: u64,
+    pub config_reloads: u64,
+    pub pidfile: String,
+    pub inbox: Option<String>,
+}
+
+impl DaemonStatus {
+    pub fn load(queue: &SqliteQueue) -> anyhow::Result<Option<Self>> {
+        match queue.control_value(CONTROL_DAEMON_STATUS)? {
+            Some(raw) => Ok(Some(
+                serde_json::from_str(&raw).context("parse daemon status")?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    pub fn is_live(&self, now: i64) -> bool {
+        self.state == "running" && now - self.heartbeat_at <= STALE_AFTER_SECS
+    }
+
+    fn save(&mut self, queue: &SqliteQueue) -> anyhow::Result<()> {
+        self.heartbeat_at = epoch_now();
+        queue.set_control(CONTROL_DAEMON_STATUS, &serde_json::to_string(self)?)
+    }
+}
+
+struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    fn acquire(queue: &SqliteQueue, path: &Path) -> anyhow::Result<Self> {
+        if let Ok(existing) = fs::read_to_string(path) {
+            let live = DaemonStatus::load(queue)?
+                .filter(|status| status.is_live(epoch_now()))
+                .filter(|status| existing.trim() == status.pid.to_string());
+            if let Some(status) = live {
+                anyhow::bail!(
+                    "daemon already running (pid {}, pidfile {})",
+                    status.pid,
+                    path.display()
+                );
+            }
+            warn!(pidfile = %path.display(), "replacing stale pidfile");
+        }
+        fs::write(path, format!("{}\n", std::process::id()))
+            .with_context(|| format!("write pidfile {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+struct InboxWatcher {
+    dir: String,
+    running: Arc<AtomicBool>,
+    handle: JoinHandle<anyhow::Result<()>>,
+}
+
+impl InboxWatcher {
+    fn spawn(queue: Arc<SqliteQueue>, dir: &str) -> Option<Self> {
+        if dir.is_empty() {
+            return None;
+        }
+        let running = Arc::new(AtomicBool::new(true));
+        let signal = running.clone();
+        let path = PathBuf::from(dir);
+        let handle = thread::spawn(move || {
+            watcher::watch_directory_until(queue.as_ref(), &path, signal.as_ref())
+        });
+        info!(inbox = dir, "inbox watcher started");
+        Some(Self {
+            dir: dir.to_string(),
+            running,
+            handle,
+        })
+    }
+
+    fn stop(self) {
+        self.running.store(false, Ordering::SeqCst);
+        match self.handle.join() {
+            Ok(Err(err)) => eprintln!("inbox watcher failed: {err}"),
+            Err(err) => eprintln!("inbox watcher thread failed: {err:?}"),
+            Ok(Ok(())) => {}
+        }
+    }
+}
+
+pub fn worker_settings(settings: &HyperionConfig) -> WorkerSettings {
+    WorkerSettings {
+        lease_seconds: settings.queue.lease_seconds,
+        poll_interval_ms: settings.worker.poll_interval_ms,
+        run_checks: settings.checks.enabled,
+        max_attempts: settings.worker.max_attempts,
+        capabilities: worker::detect_capabilities(settings),
+    }
+}
+
+pub fn max_workers(settings: &HyperionConfig) -> usize {
+    settings.worker.max_workers.max(settings.worker_count())
+}
+
+pub fn run<F>(queue: Arc<SqliteQueue>, settings: HyperionConfig, reload: F) -> anyhow::Result<()>
+where
+    F: Fn() -> anyhow::Result<HyperionConfig>,
+{
+    let terminate = Arc::new(AtomicBool::new(false));
+    let reload_requested = Arc::new(AtomicBool::new(false));
+    install_signals(&terminate, &reload_requested)?;
+    let pidfile = PidFile::acquire(queue.as_ref(), &settings.daemon.pidfile)?;
+
+    let running = Arc::new(AtomicBool::new(true));
+    let mut pool = WorkerPool::new(
+        queue.clone(),
+        worker_settings(&settings),
+        max_workers(&settings),
+    );
+    let mut target = pool.scale_to(settings.worker_count());
+    queue.set_control(CONTROL_DESIRED_WORKERS, &target.to_string())?;
+
+    let fs_root = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let fs_handle = fs_watch::spawn_fs_monitor(
+        fs_root,
+        queue.clone(),
+        Arc::new(Mutex::new(VecDeque::new())),
+        running.clone(),
+    )?;
+    let mut inbox = InboxWatcher::spawn(queue.clone(), &settings.daemon.inbox);
+
+    let mut status = DaemonStatus {
+        pid: std::process::id(),
+        state: "running".to_string(),
+        started_at: epoch_now(),
+        heartbeat_at: 0,
+        workers: target,
+        max_workers: pool.max_workers(),
+        worker_restarts: 0,
+        config_reloads: 0,
+        pidfile: pidfile.path.display().to_string(),
+        inbox: inbox.as_ref().map(|watcher| watcher.dir.clone()),
+    };
+    status.save(queue.as_ref())?;
+    info!(
+        pid = status.pid,
+        workers = target,
+        queue = queue.name(),
+        subscriptions = ?queue.subscriptions(),
+        "daemon started"
+    );
+
+    let mut current = settings;
+    let mut next_status = Instant::now() + STATUS_INTERVAL;
+    while !terminate.load(Ordering::SeqCst) {
+        if reload_requested.swap(false, Ordering::SeqCst) {
+            match reload() {
+                Ok(next) => {
+                    if next.queue.db != current.queue.db
+                        || next.queue.name != current.queue.name
+                        || next.subscriptions() != current.subscriptions()
+                        || next.max_in_flight() != current.max_in_flight()
+                    {
+                        warn!("queue.db, queue.name, worker.queues and [queues] changes take effect after a daemon restart");
+                    }
+                    pool.reconfigure(worker_settings(&next), max_workers(&next));
+                    if next.worker_count() != current.worker_count() {
+                        target = pool.scale_to(next.worker_count());
+                        let _ = queue.set_control(CONTROL_DESIRED_WORKERS, &target.to_string());
+                    }
+                    if inbox.as_ref().map(|watcher| watcher.dir.as_str())
+                        != Some(next.daemon.inbox.as_str()).filter(|dir| !dir.is_empty())
+                    {
+                        if let Some(previous) = inbox.take() {
+                            previous.stop();
+                        }
+                        inbox = InboxWatcher::spawn(queue.clone(), &next.daemon.inbox);
+                    }
+                    current = next;
+                    status.config_reloads += 1;
+                    info!("configuration reloaded");
+                }
+                Err(err) => warn!(error = %err, "config reload failed; keeping previous settings"),
+            }
+        }
+
+        if let Ok(Some(desired)) = queue.desired_workers() {
+            let desired = desired.clamp(1, pool.max_workers());
+            if desired != target {
+                target = pool.scale_to(desired);
+                info!(workers = target, "worker pool rescaled");
+            }
+        }
+        pool.restart_crashed();
+        pool.reap();
+
+        if inbox
+            .as_ref()
+            .is_some_and(|watcher| watcher.handle.is_finished())
+        {
+            if let Some(crashed) = inbox.take() {
+                crashed.stop();
+            }
+            warn!("restarting inbox watcher");
+            inbox = InboxWatcher::spawn(queue.clone(), &current.daemon.inbox);
+        }
+
+        if Instant::now() >= next_status {
+            status.workers = target;
+            status.max_workers = pool.max_workers();
+            status.worker_restarts = pool.restarts();
+            status.inbox = inbox.as_ref().map(|watcher| watcher.dir.clone());
+            if let Err(err) = status.save(queue.as_ref()) {
+                eprintln!("failed to record daemon status: {err}");
+            }
+            next_status = Instant::now() + STATUS_INTERVAL;
+        }
+        thread::sleep(pool::SCALE_POLL_INTERVAL);
+    }
+
+    info!("daemon shutting down");
+    running.store(false, Ordering::SeqCst);
+    if let Some(watcher) = inbox.take() {
+        watcher.stop();
+    }
+    pool.shutdown();
+    if let Err(err) = fs_handle.join() {
+        eprintln!("fs monitor thread failed: {err:?}");
+    }
+    status.state = "stopped".to_string();
+    status.workers = 0;
+    status.save(queue.as_ref())?;
+    drop(pidfile);
+    Ok(())
+}
+
+#[cfg(unix)]
+fn install_signals(terminate: &Arc<AtomicBool>, reload: &Arc<AtomicBool>) -> anyhow::Result<()> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+    signal_hook::flag::register(SIGINT, terminate.clone())?;
+    signal_hook::flag::register(SIGTERM, terminate.clone())?;
+    signal_hook::flag::register(SIGHUP, reload.clone())?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn install_signals(terminate: &Arc<AtomicBool>, _reload: &Arc<AtomicBool>) -> anyhow::Result<()> {
+    let signal = terminate.clone();
+    ctrlc::set_handler(move || {
+        signal.store(true, Ordering::SeqCst);
+    })?;
+    Ok(())
+}
+
+pub fn epoch_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_pidfile_of_live_daemon_and_replaces_stale_one() {
+        let base = std::env::temp_dir().join(format!("hyperion-daemon-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        fs::create_dir_all(&base).unwrap();
+        let queue = SqliteQueue::open(&base.join("queue.db")).unwrap();
+        let pidfile = base.join("hyperion.pid");
+
+        let mut status = DaemonStatus {
+            pid: 4242,
+            state: "running".to_string(),
+            started_at: epoch_now(),
+            heartbeat_at: 0,
+            workers: 2,
+            max_workers: 4,
+            worker_restarts: 0,
+            config_reloads: 0,
+            pidfile: pidfile.display().to_string(),
+            inbox: None,
+        };
+        status.save(&queue).unwrap();
+        fs::write(&pidfile, "4242\n").unwrap();
+        let err = PidFile::acquire(&queue, &pidfile).err().unwrap();
+        assert!(err.to_string().contains("already running (pid 4242"));
+
+        status.state = "stopped".to_string();
+        status.save(&queue).unwrap();
+        let loaded = DaemonStatus::load(&queue).unwrap().unwrap();
+        assert!(!loaded.is_live(epoch_now()));
+        let acquired = PidFile::acquire(&queue, &pidfile).unwrap();
+        assert_eq!(
+            fs::read_to_string(&pidfile).unwrap().trim(),
+            std::process::id().to_string()
+        );
+        drop(acquired);
+        assert!(!pidfile.exists());
+        let _ = fs::remove_dir_all(&base);
+    }
+}
@@ -3,17 +3,14 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use anyhow::Context;
 use serde_json::json;
 
-use crate::queue::{
-    SqliteQueue, DEFAULT_APPLIED_RETENTION_SECS, DEFAULT_DEADLETTER_RETENTION_SECS,
-    DEFAULT_DEDUP_WINDOW_SECS,
-};
+use crate::config::RetentionSection;
+use crate::queue::{SqliteQueue, DEFAULT_DEDUP_WINDOW_SECS, WORKER_STALE_SECS};
 
-pub fn run(queue: &SqliteQueue) -> anyhow::Result<()> {
+pub fn run(queue: &SqliteQueue, retention: &RetentionSection) -> anyhow::Result<()> {
     queue.verify_schema()?;
     queue.wal_checkpoint()?;
-    let stale_applied = queue.count_applied_older_than(DEFAULT_APPLIED_RETENTION_SECS)?;
-    let stale_dead_letters =
-        queue.count_dead_letters_older_than(DEFAULT_DEADLETTER_RETENTION_SECS)?;
+    let stale_applied = queue.count_applied_older_than(retention.applied_secs)?;
+    let stale_dead_letters = queue.count_dead_letters_older_than(retention.dead_letter_secs)?;
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .context("compute current timestamp")?
@@ -24,20 +21,24 @@ pub fn run(queue: &SqliteQueue) -> anyhow::Result<()> {
     let max_updated = queue.max_updated_timestamp()?;
     let timestamp_skew = max_updated.map(|value| now - value);
     let wal_stats = queue.wal_checkpoint_status()?;
+    let orphaned_leases = queue.orphaned_leases(WORKER_STALE_SECS)?;
+    let unroutable = queue.unroutable()?;
     let _ = queue.log_event(
         0,
         "doctor",
         "info",
         "diagnostics passed",
         Some(&json!({
-            "applied_retention_secs": DEFAULT_APPLIED_RETENTION_SECS,
-            "dead_letter_retention_secs": DEFAULT_DEADLETTER_RETENTION_SECS,
+            "applied_retention_secs": retention.applied_secs,
+            "dead_letter_retention_secs": retention.dead_letter_secs,
             "stale_applied_rows": stale_applied,
             "stale_dead_letters": stale_dead_letters,
             "dedup_window_secs": DEFAULT_DEDUP_WINDOW_SECS,
             "dedup_hits": dedup_hits,
             "last_cleanup": last_cleanup,
             "timestamp_skew_secs": timestamp_skew,
+            "orphaned_leases": orphaned_leases.len(),
+            "unroutable": unroutable.len(),
             "wal_checkpoint": {
                 "checkpointed": wal_stats.checkpointed,
                 "log": wal_stats.log,
@@ -46,9 +47,13 @@ pub fn run(queue: &SqliteQueue) -> anyhow::Result<()> {
         })),
     );
     println!("Queue diagnostics: schema OK");
-    println!("- applied rows older than {DEFAULT_APPLIED_RETENTION_SECS}s: {stale_applied}");
     println!(
-        "- dead letters older than {DEFAULT_DEADLETTER_RETENTION_SECS}s: {stale_dead_letters}"
+        "- applied rows older than {}s: {stale_applied}",
+        retention.applied_secs
+    );
+    println!(
+        "- dead letters older than {}s: {stale_dead_letters}",
+        retention.dead_letter_secs
     );
     println!("- dedup hits within {DEFAULT_DEDUP_WINDOW_SECS}s window: {dedup_hits}");
     if let Some(cleanup_ts) = last_cleanup {
@@ -63,5 +68,42 @@ pub fn run(queue: &SqliteQueue) -> anyhow::Result<()> {
         "- WAL checkpoint (passive): checkpointed={}, log={}, wal={}",
         wal_stats.checkpointed, wal_stats.log, wal_stats.wal
     );
+    if orphaned_leases.is_empty() {
+        println!("- no dead workers holding leases");
+    } else {
+        println!(
+            "- WARNING: {} lease(s) held by dead or stopped workers:",
+            orphaned_leases.len()
+        );
+        for lease in &orphaned_leases {
+            println!(
+                "  - queue {} ({}) leased by {} [{}], last heartbeat {}s ago, lease until {}",
+                lease.queue_id,
+                lease.task_id,
+                lease.worker_id,
+                lease.worker_state.as_str(),
+                now - lease.heartbeat_at,
+                lease
+                    .leased_until
+                    .map_or_else(|| "-".to_string(), |until| until.to_string())
+            );
+        }
+    }
+    if unroutable.is_empty() {
+        println!("- every pending entry can be served by a registered worker");
+    } else {
+        println!(
+            "- WARNING: {} pending entr(ies) require capabilities no registered worker advertises:",
+            unroutable.len()
+        );
+        for record in &unroutable {
+            println!(
+                "  - queue {} ({}) requires {}",
+                record.id,
+                record.payload.task_id,
+                record.payload.requires.join(", ")
+            );
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,105 @@
+use std::{thread, time::Duration};
+
+use crate::models::QueueEvent;
+use crate::queue::SqliteQueue;
+
+pub const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+pub const EVENT_BATCH_LIMIT: usize = 500;
+
+pub struct EventFeed<'a> {
+    queue: &'a SqliteQueue,
+    cursor: i64,
+}
+
+impl<'a> EventFeed<'a> {
+    pub fn after(queue: &'a SqliteQueue, seq: i64) -> Self {
+        Self { queue, cursor: seq }
+    }
+
+    pub fn from_latest(queue: &'a SqliteQueue) -> anyhow::Result<Self> {
+        Ok(Self::after(queue, queue.latest_event_seq()?))
+    }
+
+    pub fn cursor(&self) -> i64 {
+        self.cursor
+    }
+
+    pub fn next_batch(&mut self) -> anyhow::Result<Vec<QueueEvent>> {
+        let events = self.queue.events_after(self.cursor, EVENT_BATCH_LIMIT)?;
+        if let Some(last) = events.last() {
+            self.cursor = last.seq;
+        }
+        Ok(events)
+    }
+
+    pub fn wait(&self) {
+        thread::sleep(EVENT_POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ChangeOperation, ChangeRequest, OperationKind};
+
+    #[test]
+    fn records_transitions_logs_and_file_events_in_sequence() {
+        let db = std::env::temp_dir().join(format!("hyperion-events-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&db);
+        let queue = SqliteQueue::open(&db).unwrap();
+        let mut feed = EventFeed::from_latest(&queue).unwrap();
+        assert!(feed.next_batch().unwrap().is_empty());
+
+        let id = queue
+            .enqueue(&ChangeRequest {
+                task_id: "EVT-1".to_string(),
+                agent: "agent-1".to_string(),
+                changes: vec![ChangeOperation {
+                    path: "src/lib.rs".to_string(),
+                    operation: OperationKind::Update,
+                    patch: "patch".to_string(),
+                    patch_hash: None,
+                    base_hash: None,
+                }],
+                checks: vec!["true".to_string()],
+                requires: vec![],
+            })
+            .unwrap();
+        queue.dequeue(Duration::from_secs(30), "worker-1").unwrap();
+        queue
+            .log_event(id, "EVT-1", "info", "applying", None)
+            .unwrap();
+        queue.mark_applied(id).unwrap();
+        queue
+            .record_file_event("src/lib.rs", "modify", "fs", None)
+            .unwrap();
+
+        let events = feed.next_batch().unwrap();
+        let summary: Vec<(String, Option<String>)> = events
+            .iter()
+            .map(|event| {
+                (
+                    event.kind.clone(),
+                    event.data["to"].as_str().map(str::to_string),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("status".to_string(), Some("pending".to_string())),
+                ("status".to_string(), Some("in_progress".to_string())),
+                ("log".to_string(), None),
+                ("status".to_string(), Some("applied".to_string())),
+                ("file".to_string(), None),
+            ]
+        );
+        assert!(events.windows(2).all(|pair| pair[0].seq < pair[1].seq));
+        assert_eq!(events[1].data["from"], "pending");
+        assert_eq!(feed.cursor(), events.last().unwrap().seq);
+
+        let mut resumed = EventFeed::after(&queue, events[2].seq);
+        assert_eq!(resumed.next_batch().unwrap().len(), 2);
+        let _ = std::fs::remove_file(&db);
+    }
+}
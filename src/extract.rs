@@ -0,0 +1,195 @@
+use std::error::Error as StdError;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::models::ChangeRequest;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CandidateDiagnostic {
+    pub source: String,
+    pub offset: usize,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractionFailure {
+    pub response_len: usize,
+    pub candidates: Vec<CandidateDiagnostic>,
+}
+
+impl fmt::Display for ExtractionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.candidates.is_empty() {
+            return write!(
+                f,
+                "no JSON object found in agent response ({} bytes)",
+                self.response_len
+            );
+        }
+        write!(
+            f,
+            "none of {} candidate JSON object(s) parsed as a change request",
+            self.candidates.len()
+        )?;
+        for candidate in &self.candidates {
+            write!(
+                f,
+                "; {} at byte {}: {}",
+                candidate.source, candidate.offset, candidate.error
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl StdError for ExtractionFailure {}
+
+struct Candidate<'a> {
+    source: String,
+    offset: usize,
+    text: &'a str,
+}
+
+pub fn extract_change_request(response: &str) -> Result<ChangeRequest, ExtractionFailure> {
+    let mut diagnostics = Vec::new();
+    let mut seen = Vec::new();
+    for candidate in candidates(response) {
+        let trimmed = candidate.text.trim();
+        if trimmed.is_empty() || seen.contains(&trimmed) {
+            continue;
+        }
+        seen.push(trimmed);
+        match serde_json::from_str::<ChangeRequest>(trimmed) {
+            Ok(request) => return Ok(request),
+            Err(err) => diagnostics.push(CandidateDiagnostic {
+                source: candidate.source,
+                offset: candidate.offset,
+                error: err.to_string(),
+            }),
+        }
+    }
+    Err(ExtractionFailure {
+        response_len: response.len(),
+        candidates: diagnostics,
+    })
+}
+
+fn candidates(response: &str) -> Vec<Candidate<'_>> {
+    let mut found = Vec::new();
+    let trimmed = response.trim_start();
+    if trimmed.starts_with('{') {
+        found.push(Candidate {
+            source: "whole response".to_string(),
+            offset: response.len() - trimmed.len(),
+            text: response,
+        });
+    }
+    for (index, (offset, block)) in fenced_blocks(response).into_iter().enumerate() {
+        found.push(Candidate {
+            source: format!("fenced block #{}", index + 1),
+            offset,
+            text: block,
+        });
+    }
+    for (index, (offset, object)) in balanced_objects(response).into_iter().enumerate() {
+        found.push(Candidate {
+            source: format!("brace scan #{}", index + 1),
+            offset,
+            text: object,
+        });
+    }
+    found
+}
+
+fn fenced_blocks(response: &str) -> Vec<(usize, &str)> {
+    let mut blocks = Vec::new();
+    let mut cursor = 0;
+    while let Some(open) = response[cursor..].find("```") {
+        let fence_start = cursor + open + 3;
+        let body_start = match response[fence_start..].find('\n') {
+            Some(newline) => fence_start + newline + 1,
+            None => break,
+        };
+        match response[body_start..].find("```") {
+            Some(close) => {
+                blocks.push((body_start, &response[body_start..body_start + close]));
+                cursor = body_start + close + 3;
+            }
+            None => {
+                blocks.push((body_start, &response[body_start..]));
+                break;
+            }
+        }
+    }
+    blocks
+}
+
+fn balanced_objects(response: &str) -> Vec<(usize, &str)> {
+    let mut objects = Vec::new();
+    let mut depth = 0usize;
+    let mut start = None;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, ch) in response.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match ch {
+            '"' if depth > 0 => in_string = true,
+            '{' => {
+                if depth == 0 {
+                    start = Some(index);
+                }
+                depth += 1;
+            }
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(begin) = start.take() {
+                        objects.push((begin, &response[begin..=index]));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    objects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST: &str = r#"{"task_id":"T-1","agent":"a","changes":[{"path":"src/lib.rs","operation":"update","patch":"--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-{\n+}\n"}],"checks":["cargo test"]}"#;
+
+    #[test]
+    fn extracts_from_fenced_and_chatty_output() {
+        let fenced = format!("Sure! Here you go:\n```json\n{REQUEST}\n```\nLet me know.");
+        assert_eq!(extract_change_request(&fenced).unwrap().task_id, "T-1");
+
+        let chatty = format!(
+            "First a scratch object {{\"note\": \"ignore me\"}} then the answer {REQUEST} and some trailing prose."
+        );
+        assert_eq!(extract_change_request(&chatty).unwrap().task_id, "T-1");
+    }
+
+    #[test]
+    fn reports_why_each_candidate_failed() {
+        let failure = extract_change_request("```json\n{\"task_id\": \"T-1\"}\n```").unwrap_err();
+        assert_eq!(failure.candidates.len(), 1);
+        assert_eq!(failure.candidates[0].source, "fenced block #1");
+        assert!(failure.candidates[0].error.contains("missing field"));
+
+        let empty = extract_change_request("I could not complete the task.").unwrap_err();
+        assert!(empty.candidates.is_empty());
+        assert!(empty.to_string().contains("no JSON object found"));
+    }
+}
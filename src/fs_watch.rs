@@ -28,7 +28,14 @@ pub fn spawn_fs_monitor(
         .watch(&root, RecursiveMode::Recursive)
         .context("start fs watcher")?;
 
+    let db_name = queue
+        .path()
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+        .to_string();
     let handle = thread::spawn(move || {
+        let _watcher = watcher;
         while running.load(Ordering::SeqCst) {
             match rx.recv_timeout(Duration::from_secs(1)) {
                 Ok(Ok(event)) => {
@@ -37,6 +44,13 @@ pub fn spawn_fs_monitor(
                         EventKind::Modify(_) | EventKind::Create(_) | EventKind::Remove(_)
                     ) {
                         for path in event.paths {
+                            let is_queue_db =
+                                path.file_name().and_then(|name| name.to_str()).is_some_and(
+                                    |name| !db_name.is_empty() && name.starts_with(&db_name),
+                                );
+                            if is_queue_db {
+                                continue;
+                            }
                             if let Some(display) = path.to_str() {
                                 let trimmed = display.trim();
                                 let mut files = modified_files.lock().unwrap();
@@ -0,0 +1,143 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+pub const DEFAULT_AGENT_CONCURRENCY: usize = 3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct AgentLimits {
+    pub max_concurrent_per_harness: usize,
+    pub calls_per_minute: Option<u32>,
+}
+
+impl Default for AgentLimits {
+    fn default() -> Self {
+        Self {
+            max_concurrent_per_harness: DEFAULT_AGENT_CONCURRENCY,
+            calls_per_minute: None,
+        }
+    }
+}
+
+pub struct Semaphore {
+    permits: Mutex<usize>,
+    available: Condvar,
+}
+
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl Semaphore {
+    pub fn new(permits: usize) -> Self {
+        Self {
+            permits: Mutex::new(permits.max(1)),
+            available: Condvar::new(),
+        }
+    }
+
+    pub fn acquire(&self) -> SemaphorePermit<'_> {
+        let mut permits = self.permits.lock().unwrap();
+        while *permits == 0 {
+            permits = self.available.wait(permits).unwrap();
+        }
+        *permits -= 1;
+        SemaphorePermit { semaphore: self }
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        *self.semaphore.permits.lock().unwrap() += 1;
+        self.semaphore.available.notify_one();
+    }
+}
+
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn per_minute(calls: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(60) / calls.max(1),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn wait(&self) {
+        let wait = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let now = Instant::now();
+            let slot = (*next_slot).max(now);
+            *next_slot = slot + self.interval;
+            slot - now
+        };
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+pub struct AgentLimiter {
+    limits: AgentLimits,
+    harnesses: Mutex<HashMap<String, Arc<Semaphore>>>,
+    rate: Option<RateLimiter>,
+}
+
+impl AgentLimiter {
+    pub fn new(limits: AgentLimits) -> Self {
+        Self {
+            limits,
+            harnesses: Mutex::new(HashMap::new()),
+            rate: limits.calls_per_minute.map(RateLimiter::per_minute),
+        }
+    }
+
+    pub fn run<T>(&self, harness: &str, call: impl FnOnce() -> T) -> T {
+        let semaphore = {
+            let mut harnesses = self.harnesses.lock().unwrap();
+            harnesses
+                .entry(harness.to_string())
+                .or_insert_with(|| Arc::new(Semaphore::new(self.limits.max_concurrent_per_harness)))
+                .clone()
+        };
+        let _permit = semaphore.acquire();
+        if let Some(rate) = self.rate.as_ref() {
+            rate.wait();
+        }
+        call()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn caps_concurrent_calls_per_harness() {
+        let limiter = AgentLimiter::new(AgentLimits {
+            max_concurrent_per_harness: 2,
+            calls_per_minute: None,
+        });
+        let active = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            for _ in 0..6 {
+                scope.spawn(|| {
+                    limiter.run("copilot", || {
+                        let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        std::thread::sleep(Duration::from_millis(20));
+                        active.fetch_sub(1, Ordering::SeqCst);
+                    });
+                });
+            }
+        });
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+}
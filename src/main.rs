@@ -5,17 +5,27 @@ use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc, Mutex,
 };
-use std::thread;
 
 use clap::{Parser, Subcommand};
 
 mod agent;
+mod api;
 mod apply;
+mod config;
+mod context;
+mod daemon;
 mod doctor;
+mod events;
 mod exporter;
+mod extract;
 mod fs_watch;
+mod limits;
+mod mcp;
+mod migrations;
 mod models;
 mod orchestrator;
+mod pool;
+mod prompt;
 mod queue;
 mod request;
 mod runner;
@@ -25,16 +35,21 @@ mod watcher;
 mod worker;
 
 use crate::agent::AgentHarness;
-use crate::request::DEFAULT_MODEL;
-use models::QueueStatus;
-use queue::{SqliteQueue, DEFAULT_APPLIED_RETENTION_SECS};
+use models::{QueueMode, QueueStatus};
+use queue::SqliteQueue;
 use serde_json::to_string_pretty;
 
 #[derive(Parser)]
 #[command(name = "hyperion", version, about = "Multi-agent orchestration queue")]
 struct Cli {
-    #[arg(long, default_value = "hyperion.db")]
-    db: PathBuf,
+    #[arg(long)]
+    db: Option<PathBuf>,
+
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    #[arg(long, global = true)]
+    queue: Option<String>,
 
     #[command(subcommand)]
     command: Option<Commands>,
@@ -43,19 +58,101 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     Run {
-        #[arg(long, default_value_t = 3)]
-        workers: usize,
-        #[arg(long, default_value_t = 3)]
-        agents: usize,
+        #[arg(long)]
+        workers: Option<usize>,
+        #[arg(long)]
+        agents: Option<usize>,
+        #[arg(long)]
+        max_workers: Option<usize>,
+        #[arg(long)]
+        headless: bool,
+        #[arg(long = "subscribe")]
+        subscriptions: Vec<String>,
+    },
+    Daemon {
+        #[arg(long)]
+        workers: Option<usize>,
+        #[arg(long)]
+        max_workers: Option<usize>,
+        #[arg(long)]
+        pidfile: Option<PathBuf>,
+        #[arg(long)]
+        inbox: Option<PathBuf>,
+        #[arg(long = "subscribe")]
+        subscriptions: Vec<String>,
+    },
+    Status {
+        #[arg(long)]
+        format: Option<String>,
     },
     Request {
         file: PathBuf,
         #[arg(long)]
         model: Option<String>,
-        #[arg(long, default_value_t = 3)]
-        agents: usize,
-        #[arg(long, default_value_t = 3)]
-        workers: usize,
+        #[arg(long)]
+        agents: Option<usize>,
+        #[arg(long)]
+        workers: Option<usize>,
+        #[arg(long)]
+        max_agent_concurrency: Option<usize>,
+        #[arg(long)]
+        agent_rate_limit: Option<u32>,
+        #[arg(long)]
+        replay: Option<PathBuf>,
+        #[arg(long)]
+        record: Option<PathBuf>,
+        #[arg(long)]
+        prompts: Option<PathBuf>,
+    },
+    Serve {
+        #[arg(long)]
+        addr: Option<String>,
+        #[arg(long)]
+        socket: Option<PathBuf>,
+        #[arg(long)]
+        token: Option<String>,
+    },
+    Mcp {
+        #[arg(long, default_value = "mcp-agent")]
+        agent: String,
+    },
+    Events {
+        #[arg(long)]
+        since_seq: Option<i64>,
+        #[arg(long)]
+        follow: bool,
+    },
+    ScaleWorkers {
+        count: usize,
+    },
+    Pause {
+        #[arg(long)]
+        agent: Option<String>,
+    },
+    Resume {
+        #[arg(long)]
+        agent: Option<String>,
+    },
+    Drain {
+        #[arg(long)]
+        wait: bool,
+    },
+    Prompt {
+        #[command(subcommand)]
+        command: PromptCommands,
+    },
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    Db {
+        #[command(subcommand)]
+        command: DbCommands,
+    },
+    RequestStatus {
+        request_id: String,
+        #[arg(long)]
+        format: Option<String>,
     },
     Export {
         #[arg(long)]
@@ -68,8 +165,8 @@ enum Commands {
         file: PathBuf,
     },
     Dequeue {
-        #[arg(long, default_value = "300")]
-        lease_seconds: u64,
+        #[arg(long)]
+        lease_seconds: Option<u64>,
     },
     List {
         status: Option<QueueStatus>,
@@ -91,6 +188,9 @@ enum Commands {
     MarkApplied {
         id: i64,
     },
+    Cancel {
+        target: String,
+    },
     MarkFailed {
         id: i64,
         #[arg(long)]
@@ -119,16 +219,26 @@ enum Commands {
         run_checks: bool,
     },
     Worker {
-        #[arg(long, default_value = "300")]
-        lease_seconds: u64,
-        #[arg(long, default_value = "500")]
-        poll_interval_ms: u64,
         #[arg(long)]
+        lease_seconds: Option<u64>,
+        #[arg(long)]
+        poll_interval_ms: Option<u64>,
+        #[arg(long, conflicts_with = "skip_checks")]
         run_checks: bool,
-        #[arg(long, default_value = "5")]
-        max_attempts: i64,
+        #[arg(long)]
+        skip_checks: bool,
+        #[arg(long)]
+        max_attempts: Option<i64>,
         #[arg(long, default_value = "worker-cli")]
         worker_id: String,
+        #[arg(long = "label")]
+        labels: Vec<String>,
+        #[arg(long = "subscribe")]
+        subscriptions: Vec<String>,
+    },
+    Workers {
+        #[arg(long)]
+        format: Option<String>,
     },
     SessionInit {
         #[arg(long)]
@@ -152,24 +262,143 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum PromptCommands {
+    Render {
+        file: PathBuf,
+        #[arg(long, default_value = "agent-1")]
+        agent: String,
+        #[arg(long, default_value = "copilot")]
+        harness: String,
+        #[arg(long)]
+        prompts: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbCommands {
+    Migrate,
+    Status {
+        #[arg(long)]
+        format: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    Show {
+        #[arg(long)]
+        format: Option<String>,
+    },
+}
+
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
-    init_tracing();
-    let queue = Arc::new(SqliteQueue::open(&cli.db)?);
+    let overrides = cli_overrides(&cli);
+    let loaded = config::LoadedConfig::load(cli.config.as_deref(), &overrides)?;
+    let settings = loaded.config.clone();
+    init_tracing(settings.tui.console_log);
+    if let Some(Commands::Db { command }) = &cli.command {
+        return db_command(&settings.queue.db, command);
+    }
+    let queue = Arc::new(open_queue(&settings)?);
 
     match cli.command {
-        None => run_integrated(queue.clone(), cli.db.clone(), 3, 3),
-        Some(Commands::Run { workers, agents }) => {
-            run_integrated(queue.clone(), cli.db.clone(), workers, agents)
+        Some(Commands::Run { headless: true, .. }) | Some(Commands::Daemon { .. }) => {
+            daemon::run(queue.clone(), settings, || {
+                Ok(config::LoadedConfig::load(cli.config.as_deref(), &overrides)?.config)
+            })
+        }
+        None | Some(Commands::Run { .. }) => run_integrated(queue.clone(), &settings),
+        Some(Commands::Status { format }) => {
+            let status = daemon::DaemonStatus::load(queue.as_ref())?;
+            let live = status
+                .as_ref()
+                .is_some_and(|status| status.is_live(daemon::epoch_now()));
+            if format.as_deref() == Some("json") {
+                println!(
+                    "{}",
+                    to_string_pretty(&serde_json::json!({
+                        "live": live,
+                        "daemon": status,
+                        "queue": queue.name(),
+                        "mode": queue.queue_mode()?,
+                        "paused_agents": queue.paused_agents()?,
+                    }))?
+                );
+                return Ok(());
+            }
+            match status {
+                None => println!(
+                    "daemon: never started against {}",
+                    settings.queue.db.display()
+                ),
+                Some(status) => {
+                    let state = if live {
+                        "running"
+                    } else if status.state == "running" {
+                        "unresponsive"
+                    } else {
+                        status.state.as_str()
+                    };
+                    println!(
+                        "daemon: {state} (pid {}, started {}, last heartbeat {})",
+                        status.pid, status.started_at, status.heartbeat_at
+                    );
+                    println!(
+                        "workers: {} (max {}), restarts: {}, config reloads: {}",
+                        status.workers,
+                        status.max_workers,
+                        status.worker_restarts,
+                        status.config_reloads
+                    );
+                    println!(
+                        "pidfile: {}, inbox: {}",
+                        status.pidfile,
+                        status.inbox.as_deref().unwrap_or("disabled")
+                    );
+                }
+            }
+            let counts = queue.queue_metrics(None)?.status_counts;
+            println!(
+                "queue {}: {} pending={} in_progress={} applied={} failed={} cancelled={}",
+                queue.name(),
+                queue.queue_mode()?.as_str(),
+                counts.pending,
+                counts.in_progress,
+                counts.applied,
+                counts.failed,
+                counts.cancelled
+            );
+            let paused_agents = queue.paused_agents()?;
+            if !paused_agents.is_empty() {
+                let names: Vec<&str> = paused_agents.iter().map(String::as_str).collect();
+                println!("paused agents: {}", names.join(", "));
+            }
+            Ok(())
         }
         Some(Commands::Request {
             file,
-            model,
-            agents,
+            replay,
+            record,
             ..
         }) => {
-            let agent_count = agents.clamp(1, 3);
-            let enqueued = request::handle_request(queue.as_ref(), &file, model, agent_count)?;
+            let agents = &settings.agents;
+            let options = request::RequestOptions {
+                harness: agents.harness.clone(),
+                model: Some(agents.model.clone()),
+                max_agents: agents.count.max(1),
+                transcripts: request::TranscriptOptions {
+                    replay_dir: replay,
+                    record_dir: record,
+                },
+                templates: prompt::PromptTemplates::new(&agents.prompts_dir),
+                limits: limits::AgentLimits {
+                    max_concurrent_per_harness: agents.max_concurrency.max(1),
+                    calls_per_minute: Some(agents.rate_limit_per_minute).filter(|rate| *rate > 0),
+                },
+            };
+            let enqueued = request::handle_request(queue.as_ref(), &file, options)?;
             println!(
                 "Processed request {} and enqueued {} change request(s)",
                 file.display(),
@@ -177,6 +406,145 @@ fn main() -> anyhow::Result<()> {
             );
             Ok(())
         }
+        Some(Commands::Serve { .. }) => serve(queue.clone(), &settings.api),
+        Some(Commands::Mcp { agent }) => {
+            let mut server =
+                mcp::McpServer::new(queue.as_ref(), agent, settings.checks.allowed.clone());
+            server.serve(std::io::stdin().lock(), std::io::stdout().lock())
+        }
+        Some(Commands::Events { since_seq, follow }) => {
+            let mut feed = match since_seq {
+                Some(seq) => events::EventFeed::after(queue.as_ref(), seq),
+                None if follow => events::EventFeed::from_latest(queue.as_ref())?,
+                None => events::EventFeed::after(queue.as_ref(), 0),
+            };
+            let running = Arc::new(AtomicBool::new(true));
+            let signal = running.clone();
+            ctrlc::set_handler(move || {
+                signal.store(false, Ordering::SeqCst);
+            })?;
+            while running.load(Ordering::SeqCst) {
+                let batch = feed.next_batch()?;
+                for event in &batch {
+                    println!("{}", serde_json::to_string(event)?);
+                }
+                if batch.is_empty() {
+                    if !follow {
+                        break;
+                    }
+                    feed.wait();
+                }
+            }
+            Ok(())
+        }
+        Some(Commands::ScaleWorkers { count }) => {
+            let count = count.max(1);
+            queue.set_control(queue::CONTROL_DESIRED_WORKERS, &count.to_string())?;
+            println!("Requested {count} worker(s); running pools rescale within a second");
+            Ok(())
+        }
+        Some(Commands::Pause { agent: Some(agent) }) => {
+            queue.set_agent_paused(&agent, true)?;
+            println!("Paused agent {agent}; workers skip its change requests until resumed");
+            Ok(())
+        }
+        Some(Commands::Pause { agent: None }) => {
+            queue.set_queue_mode(QueueMode::Paused)?;
+            println!("Queue paused; workers stop dequeuing after their current change request");
+            Ok(())
+        }
+        Some(Commands::Resume { agent: Some(agent) }) => {
+            queue.set_agent_paused(&agent, false)?;
+            println!("Resumed agent {agent}");
+            Ok(())
+        }
+        Some(Commands::Resume { agent: None }) => {
+            queue.set_queue_mode(QueueMode::Running)?;
+            println!("Queue resumed");
+            Ok(())
+        }
+        Some(Commands::Drain { wait }) => {
+            queue.set_queue_mode(QueueMode::Draining)?;
+            println!("Queue draining; no new change requests will be dequeued");
+            if wait {
+                loop {
+                    let in_flight = queue.in_flight_count()?;
+                    if in_flight == 0 {
+                        break;
+                    }
+                    println!("waiting for {in_flight} in-flight change request(s)");
+                    std::thread::sleep(std::time::Duration::from_secs(1));
+                }
+                queue.set_queue_mode(QueueMode::Paused)?;
+                println!("Drained; queue is paused until `hyperion resume`");
+            }
+            Ok(())
+        }
+        Some(Commands::Prompt {
+            command:
+                PromptCommands::Render {
+                    file,
+                    agent,
+                    harness,
+                    ..
+                },
+        }) => {
+            let contents = std::fs::read_to_string(file)?;
+            let assignment: models::TaskAssignment = serde_json::from_str(&contents)?;
+            let templates = prompt::PromptTemplates::new(&settings.agents.prompts_dir);
+            let selected = templates.select(&assignment.task_id, &harness)?;
+            eprintln!("template: {}", selected.source);
+            let rendered =
+                prompt::build_prompt(queue.as_ref(), &templates, &assignment, &agent, &harness)?;
+            print!("{rendered}");
+            Ok(())
+        }
+        Some(Commands::Config {
+            command: ConfigCommands::Show { format },
+        }) => {
+            if format.as_deref() == Some("json") {
+                println!("{}", to_string_pretty(&loaded.effective())?);
+            } else {
+                print!("{}", loaded.render());
+            }
+            Ok(())
+        }
+        Some(Commands::Db { .. }) => unreachable!("db commands run before the queue is opened"),
+        Some(Commands::RequestStatus { request_id, format }) => {
+            let status = queue
+                .request_status(&request_id)?
+                .ok_or_else(|| anyhow::anyhow!("unknown task request {request_id}"))?;
+            if format.as_deref() == Some("json") {
+                println!("{}", to_string_pretty(&status)?);
+            } else {
+                println!(
+                    "{} {} ({} assignment(s)): {}",
+                    status.request_id,
+                    status.rollup.as_str(),
+                    status.assignments.len(),
+                    status.summary
+                );
+                for assignment in status.assignments {
+                    println!(
+                        "  {} {} queue_id={} queue_status={} agent={} attempts={} error={:?}",
+                        assignment.task_id,
+                        assignment.state.as_str(),
+                        assignment
+                            .queue_id
+                            .map(|id| id.to_string())
+                            .unwrap_or_else(|| "-".to_string()),
+                        assignment
+                            .queue_status
+                            .map(|status| status.as_str())
+                            .unwrap_or("-"),
+                        assignment.agent.as_deref().unwrap_or("-"),
+                        assignment.attempts.unwrap_or(0),
+                        assignment.error
+                    );
+                }
+            }
+            Ok(())
+        }
         Some(Commands::Export { dest, overwrite }) => {
             let target =
                 dest.unwrap_or_else(|| env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
@@ -184,7 +552,7 @@ fn main() -> anyhow::Result<()> {
             Ok(())
         }
         Some(Commands::Init) => {
-            println!("Initialized queue at {}", cli.db.display());
+            println!("Initialized queue at {}", settings.queue.db.display());
             Ok(())
         }
         Some(Commands::Enqueue { file }) => {
@@ -194,10 +562,9 @@ fn main() -> anyhow::Result<()> {
             println!("Enqueued change request {id}");
             Ok(())
         }
-        Some(Commands::Dequeue { lease_seconds }) => {
-            if let Some(record) =
-                queue.dequeue(std::time::Duration::from_secs(lease_seconds), "cli")?
-            {
+        Some(Commands::Dequeue { .. }) => {
+            let lease = std::time::Duration::from_secs(settings.queue.lease_seconds);
+            if let Some(record) = queue.dequeue(lease, "cli")? {
                 println!(
                     "Dequeued {} from {} (attempt {})",
                     record.id, record.payload.task_id, record.attempts
@@ -271,6 +638,7 @@ fn main() -> anyhow::Result<()> {
             println!("Marked {id} as applied");
             Ok(())
         }
+        Some(Commands::Cancel { target }) => cancel(queue.as_ref(), &target),
         Some(Commands::MarkFailed { id, error }) => {
             queue.mark_failed(id, error)?;
             println!("Marked {id} as failed");
@@ -282,11 +650,11 @@ fn main() -> anyhow::Result<()> {
         }
         Some(Commands::SessionInit {
             resume_id,
-            model,
             allow_all_tools,
+            ..
         }) => {
-            let model = model.unwrap_or_else(|| DEFAULT_MODEL.to_string());
-            let session = queue.upsert_agent_session(&resume_id, &model, allow_all_tools)?;
+            let model = &settings.agents.model;
+            let session = queue.upsert_agent_session(&resume_id, model, allow_all_tools)?;
             println!(
                 "Recorded agent session {} (resume={}, model={}, allow_all_tools={})",
                 session.id, session.resume_id, session.model, session.allow_all_tools
@@ -312,11 +680,11 @@ fn main() -> anyhow::Result<()> {
             Ok(())
         }
         Some(Commands::Tui) => {
-            tui::run_dashboard(queue.as_ref())?;
+            tui::run_dashboard(queue.as_ref(), &settings.tui)?;
             Ok(())
         }
-        Some(Commands::Agent { model, prompt }) => {
-            let harness = agent::CopilotHarness::new(model.unwrap_or_else(|| "gpt-5-mini".into()));
+        Some(Commands::Agent { prompt, .. }) => {
+            let harness = agent::CopilotHarness::new(settings.agents.model.clone());
             let response = harness.run(&prompt)?;
             println!("{response}");
             Ok(())
@@ -364,24 +732,71 @@ fn main() -> anyhow::Result<()> {
             println!("applied");
             Ok(())
         }
-        Some(Commands::Worker {
-            lease_seconds,
-            poll_interval_ms,
-            run_checks,
-            max_attempts,
-            worker_id,
-        }) => worker::run_worker(
+        Some(Commands::Worker { worker_id, .. }) => worker::run_worker(
             queue.as_ref(),
             worker::WorkerConfig {
                 worker_id,
-                lease_seconds,
-                poll_interval_ms,
-                run_checks,
-                max_attempts,
+                lease_seconds: settings.queue.lease_seconds,
+                poll_interval_ms: settings.worker.poll_interval_ms,
+                run_checks: settings.checks.enabled,
+                max_attempts: settings.worker.max_attempts,
+                capabilities: worker::detect_capabilities(&settings),
             },
         ),
+        Some(Commands::Workers { format }) => {
+            let workers = queue.list_workers()?;
+            let now = daemon::epoch_now();
+            if format.as_deref() == Some("json") {
+                let rows: Vec<_> = workers
+                    .iter()
+                    .map(|worker| {
+                        serde_json::json!({
+                            "worker": worker,
+                            "dead": worker.is_dead(now, queue::WORKER_STALE_SECS),
+                        })
+                    })
+                    .collect();
+                println!("{}", to_string_pretty(&rows)?);
+                return Ok(());
+            }
+            if workers.is_empty() {
+                println!(
+                    "No workers have registered with {}",
+                    settings.queue.db.display()
+                );
+            }
+            for worker in workers {
+                let state = if worker.is_dead(now, queue::WORKER_STALE_SECS) {
+                    "dead"
+                } else {
+                    worker.state.as_str()
+                };
+                let caps = &worker.capabilities;
+                println!(
+                    "{} [{state}] host={} pid={} queues={} heartbeat={}s ago current={} checks={} cargo={} network={} worktree={} labels={}",
+                    worker.worker_id,
+                    worker.hostname,
+                    worker.pid,
+                    worker.queues.join(","),
+                    now - worker.heartbeat_at,
+                    worker
+                        .current_queue_id
+                        .map_or_else(|| "-".to_string(), |id| id.to_string()),
+                    caps.run_checks,
+                    caps.cargo,
+                    caps.network,
+                    caps.worktree,
+                    if caps.labels.is_empty() {
+                        "-".to_string()
+                    } else {
+                        caps.labels.join(",")
+                    }
+                );
+            }
+            Ok(())
+        }
         Some(Commands::Doctor) => {
-            doctor::run(queue.as_ref())?;
+            doctor::run(queue.as_ref(), &settings.queue_retention())?;
             Ok(())
         }
         Some(Commands::QueueMetrics { since, format }) => {
@@ -396,12 +811,14 @@ fn main() -> anyhow::Result<()> {
                         .unwrap_or_else(|| "n/a".to_string())
                 };
                 println!(
-                    "Queue metrics ({}s window): pending={} in_progress={} applied={} failed={} throughput={} lease_contention_events={}",
+                    "Queue metrics for {} ({}s window): pending={} in_progress={} applied={} failed={} cancelled={} throughput={} lease_contention_events={}",
+                    metrics.queue,
                     metrics.window_seconds,
                     counts.pending,
                     counts.in_progress,
                     counts.applied,
                     counts.failed,
+                    counts.cancelled,
                     formatted(metrics.throughput_per_minute, "/min"),
                     metrics.lease_contention_events
                 );
@@ -411,37 +828,336 @@ fn main() -> anyhow::Result<()> {
                     formatted(metrics.avg_apply_duration_ms, "ms"),
                     formatted(metrics.avg_poll_interval_ms, "ms"),
                 );
+                if metrics.queues.len() > 1 {
+                    for (name, counts) in &metrics.queues {
+                        let marker = if *name == metrics.queue { "*" } else { " " };
+                        println!(
+                            "  {marker}{name}: pending={} in_progress={} applied={} failed={} cancelled={}",
+                            counts.pending,
+                            counts.in_progress,
+                            counts.applied,
+                            counts.failed,
+                            counts.cancelled
+                        );
+                    }
+                }
             }
             Ok(())
         }
         Some(Commands::Cleanup { ttl_seconds }) => {
-            let ttl = ttl_seconds.unwrap_or(DEFAULT_APPLIED_RETENTION_SECS).max(1);
+            let ttl = ttl_seconds
+                .unwrap_or(settings.queue_retention().applied_secs)
+                .max(1);
             let deleted = queue.cleanup_stale_records(ttl)?;
             println!(
-                "Removed {deleted} applied/failed entries older than {ttl} seconds via cleanup."
+                "Removed {deleted} applied/failed entries older than {ttl} seconds from queue {} via cleanup.",
+                queue.name()
             );
             Ok(())
         }
     }
 }
 
-fn init_tracing() {
+fn cli_overrides(cli: &Cli) -> config::CliOverrides {
+    let mut overrides = config::CliOverrides::default();
+    overrides.set_opt(
+        "queue.db",
+        "--db",
+        cli.db.as_ref().map(|db| db.display().to_string()),
+    );
+    overrides.set_opt("queue.name", "--queue", cli.queue.clone());
+    match &cli.command {
+        Some(Commands::Run {
+            workers,
+            agents,
+            max_workers,
+            subscriptions,
+            ..
+        }) => {
+            if !subscriptions.is_empty() {
+                overrides.set("worker.queues", "--subscribe", subscriptions.clone());
+            }
+            overrides.set_opt("worker.count", "--workers", workers.map(|n| n as i64));
+            overrides.set_opt("agents.count", "--agents", agents.map(|n| n as i64));
+            overrides.set_opt(
+                "worker.max_workers",
+                "--max-workers",
+                max_workers.map(|n| n as i64),
+            );
+        }
+        Some(Commands::Daemon {
+            workers,
+            max_workers,
+            pidfile,
+            inbox,
+            subscriptions,
+        }) => {
+            if !subscriptions.is_empty() {
+                overrides.set("worker.queues", "--subscribe", subscriptions.clone());
+            }
+            overrides.set_opt("worker.count", "--workers", workers.map(|n| n as i64));
+            overrides.set_opt(
+                "worker.max_workers",
+                "--max-workers",
+                max_workers.map(|n| n as i64),
+            );
+            overrides.set_opt(
+                "daemon.pidfile",
+                "--pidfile",
+                pidfile.as_ref().map(|path| path.display().to_string()),
+            );
+            overrides.set_opt(
+                "daemon.inbox",
+                "--inbox",
+                inbox.as_ref().map(|path| path.display().to_string()),
+            );
+        }
+        Some(Commands::Request {
+            model,
+            agents,
+            workers,
+            max_agent_concurrency,
+            agent_rate_limit,
+            prompts,
+            ..
+        }) => {
+            overrides.set_opt("agents.model", "--model", model.clone());
+            overrides.set_opt("agents.count", "--agents", agents.map(|n| n as i64));
+            overrides.set_opt("worker.count", "--workers", workers.map(|n| n as i64));
+            overrides.set_opt(
+                "agents.max_concurrency",
+                "--max-agent-concurrency",
+                max_agent_concurrency.map(|n| n as i64),
+            );
+            overrides.set_opt(
+                "agents.rate_limit_per_minute",
+                "--agent-rate-limit",
+                agent_rate_limit.map(i64::from),
+            );
+            overrides.set_opt(
+                "agents.prompts_dir",
+                "--prompts",
+                prompts.as_ref().map(|dir| dir.display().to_string()),
+            );
+        }
+        Some(Commands::Prompt {
+            command: PromptCommands::Render { prompts, .. },
+        }) => {
+            overrides.set_opt(
+                "agents.prompts_dir",
+                "--prompts",
+                prompts.as_ref().map(|dir| dir.display().to_string()),
+            );
+        }
+        Some(Commands::Serve {
+            addr,
+            socket,
+            token,
+        }) => {
+            overrides.set_opt("api.addr", "--addr", addr.clone());
+            overrides.set_opt(
+                "api.socket",
+                "--socket",
+                socket.as_ref().map(|path| path.display().to_string()),
+            );
+            overrides.set_opt("api.token", "--token", token.clone());
+        }
+        Some(Commands::Dequeue { lease_seconds }) => {
+            overrides.set_opt(
+                "queue.lease_seconds",
+                "--lease-seconds",
+                lease_seconds.map(|secs| secs as i64),
+            );
+        }
+        Some(Commands::Worker {
+            lease_seconds,
+            poll_interval_ms,
+            run_checks,
+            skip_checks,
+            max_attempts,
+            labels,
+            subscriptions,
+            ..
+        }) => {
+            if !labels.is_empty() {
+                overrides.set("worker.labels", "--label", labels.clone());
+            }
+            if !subscriptions.is_empty() {
+                overrides.set("worker.queues", "--subscribe", subscriptions.clone());
+            }
+            overrides.set_opt(
+                "queue.lease_seconds",
+                "--lease-seconds",
+                lease_seconds.map(|secs| secs as i64),
+            );
+            overrides.set_opt(
+                "worker.poll_interval_ms",
+                "--poll-interval-ms",
+                poll_interval_ms.map(|ms| ms as i64),
+            );
+            overrides.set_opt("worker.max_attempts", "--max-attempts", *max_attempts);
+            if *run_checks {
+                overrides.set("checks.enabled", "--run-checks", true);
+            }
+            if *skip_checks {
+                overrides.set("checks.enabled", "--skip-checks", false);
+            }
+        }
+        Some(Commands::SessionInit { model, .. }) | Some(Commands::Agent { model, .. }) => {
+            overrides.set_opt("agents.model", "--model", model.clone());
+        }
+        _ => {}
+    }
+    overrides
+}
+
+fn db_command(db: &std::path::Path, command: &DbCommands) -> anyhow::Result<()> {
+    match command {
+        DbCommands::Migrate => {
+            let applied = SqliteQueue::migrate(db)?;
+            if applied.is_empty() {
+                println!(
+                    "{} is already at schema version {}",
+                    db.display(),
+                    migrations::latest_version()
+                );
+            }
+            for migration in applied {
+                println!("applied {} {}", migration.version, migration.name);
+            }
+            Ok(())
+        }
+        DbCommands::Status { format } => {
+            let status = SqliteQueue::schema_status(db)?;
+            if format.as_deref() == Some("json") {
+                println!("{}", to_string_pretty(&status)?);
+                return Ok(());
+            }
+            println!(
+                "{}: schema version {} (binary supports {})",
+                db.display(),
+                status.current_version,
+                status.latest_version
+            );
+            for migration in &status.applied {
+                println!(
+                    "  applied {} {} at {}",
+                    migration.version, migration.name, migration.applied_at
+                );
+            }
+            for migration in &status.pending {
+                println!("  pending {} {}", migration.version, migration.name);
+            }
+            if status.current_version > status.latest_version {
+                println!("  database is newer than this binary; upgrade hyperion before using it");
+            }
+            Ok(())
+        }
+    }
+}
+
+fn open_queue(settings: &config::HyperionConfig) -> anyhow::Result<SqliteQueue> {
+    Ok(SqliteQueue::open(&settings.queue.db)?
+        .scoped(&settings.queue.name)?
+        .subscribed(&settings.worker.queues)?
+        .with_max_in_flight(settings.max_in_flight()))
+}
+
+fn init_tracing(console_log: bool) {
     use std::io;
     let filter = tracing_subscriber::EnvFilter::from_default_env()
         .add_directive("hyperion=info".parse().unwrap());
     let fmt = tracing_subscriber::fmt().with_env_filter(filter);
-    if std::env::var("HYPERION_LOG").is_ok() {
+    if console_log {
         let _ = fmt.try_init();
     } else {
         let _ = fmt.with_writer(io::sink).try_init();
     }
 }
 
+fn serve(queue: Arc<SqliteQueue>, settings: &config::ApiSection) -> anyhow::Result<()> {
+    let running = Arc::new(AtomicBool::new(true));
+    let signal = running.clone();
+    ctrlc::set_handler(move || {
+        signal.store(false, Ordering::SeqCst);
+    })?;
+    let token = Some(settings.token.clone()).filter(|token| !token.is_empty());
+    let auth = if token.is_some() { "token" } else { "none" };
+    let server = Arc::new(api::ApiServer::new(queue, token));
+    if !settings.socket.is_empty() {
+        #[cfg(unix)]
+        {
+            let path = PathBuf::from(&settings.socket);
+            let _ = std::fs::remove_file(&path);
+            let listener = std::os::unix::net::UnixListener::bind(&path)?;
+            println!(
+                "Serving Hyperion API on unix socket {} (auth: {auth})",
+                path.display()
+            );
+            let result = server.serve_unix(listener, running);
+            let _ = std::fs::remove_file(&path);
+            return result;
+        }
+        #[cfg(not(unix))]
+        anyhow::bail!("unix sockets are not supported on this platform");
+    }
+    let listener = api::bind_local(&settings.addr)?;
+    println!(
+        "Serving Hyperion API on http://{} (auth: {auth})",
+        listener.local_addr()?
+    );
+    server.serve_tcp(listener, running)
+}
+
+fn cancel(queue: &SqliteQueue, target: &str) -> anyhow::Result<()> {
+    let describe = |outcome: Option<models::CancelOutcome>| match outcome {
+        Some(models::CancelOutcome::Cancelled) => "cancelled".to_string(),
+        Some(models::CancelOutcome::Requested) => {
+            "cancellation requested; the worker stops it before its next step".to_string()
+        }
+        Some(models::CancelOutcome::Finished(status)) => {
+            format!("already {}", status.as_str())
+        }
+        None => "not enqueued; nothing to cancel".to_string(),
+    };
+    if let Ok(id) = target.parse::<i64>() {
+        if let Some(outcome) = queue.cancel(id)? {
+            println!("{id}: {}", describe(Some(outcome)));
+            return Ok(());
+        }
+    }
+    if let Some(outcomes) = queue.cancel_request(target)? {
+        println!("request {target}: {} assignment(s)", outcomes.len());
+        for cancellation in outcomes {
+            println!(
+                "  {}: {}",
+                cancellation.task_id,
+                describe(cancellation.outcome)
+            );
+        }
+        return Ok(());
+    }
+    if let Some(id) = queue.latest_record_for_task(target)? {
+        println!("{target} ({id}): {}", describe(queue.cancel(id)?));
+        return Ok(());
+    }
+    if queue.assignment_state(target)? == Some(models::AssignmentState::Pending) {
+        queue.update_assignment(
+            target,
+            models::AssignmentState::Cancelled,
+            "",
+            None,
+            Some("cancelled by operator".to_string()),
+        )?;
+        println!("{target}: cancelled before it was enqueued");
+        return Ok(());
+    }
+    anyhow::bail!("no queue record, task or request matches {target}")
+}
+
 fn run_integrated(
     queue: Arc<SqliteQueue>,
-    db_path: PathBuf,
-    worker_count: usize,
-    agent_count: usize,
+    settings: &config::HyperionConfig,
 ) -> anyhow::Result<()> {
     let running = Arc::new(AtomicBool::new(true));
     let signal = running.clone();
@@ -449,28 +1165,13 @@ fn run_integrated(
         signal.store(false, Ordering::SeqCst);
     })?;
 
-    let worker_count = worker_count.clamp(1, 3);
-    let agent_count = agent_count.clamp(1, 3);
-
-    let mut handles = Vec::new();
-    for index in 0..worker_count {
-        let queue = queue.clone();
-        let running = running.clone();
-        let worker_id = format!("worker-{}", index + 1);
-        handles.push(thread::spawn(move || {
-            worker::run_worker_with_signal(
-                queue.as_ref(),
-                worker::WorkerConfig {
-                    worker_id: worker_id.clone(),
-                    lease_seconds: 300,
-                    poll_interval_ms: 500,
-                    run_checks: true,
-                    max_attempts: 5,
-                },
-                running,
-            )
-        }));
-    }
+    let pool = pool::WorkerPool::new(
+        queue.clone(),
+        daemon::worker_settings(settings),
+        daemon::max_workers(settings),
+    );
+    let pool_size = pool.size_handle();
+    let pool_handle = pool::supervise(pool, settings.worker_count(), running.clone());
 
     let modified_files = Arc::new(Mutex::new(VecDeque::new()));
     let fs_root = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
@@ -482,18 +1183,18 @@ fn run_integrated(
     )?;
 
     let tui_config = tui::TuiConfig {
-        db_path: db_path.display().to_string(),
-        worker_count,
-        agent_count,
+        db_path: settings.queue.db.display().to_string(),
+        worker_count: pool_size,
+        max_workers: daemon::max_workers(settings).max(1),
+        agent_count: settings.agents.count.max(1),
+        refresh_intervals_ms: settings.tui.refresh_intervals_ms.clone(),
         modified_files: modified_files.clone(),
     };
     let tui_result = tui::run_dashboard_with_config(queue.as_ref(), tui_config);
     running.store(false, Ordering::SeqCst);
 
-    for handle in handles {
-        if let Err(err) = handle.join() {
-            eprintln!("worker thread failed: {err:?}");
-        }
+    if let Err(err) = pool_handle.join() {
+        eprintln!("worker pool thread failed: {err:?}");
     }
 
     if let Err(err) = fs_handle.join() {
@@ -0,0 +1,445 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, Write},
+    path::Path,
+};
+
+use anyhow::Context;
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::apply;
+use crate::context;
+use crate::models::{AssignmentState, ChangeRequest};
+use crate::queue::SqliteQueue;
+use crate::runner;
+use crate::validator;
+
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+const MAX_READ_LINES: usize = 2000;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+pub struct McpServer<'a> {
+    queue: &'a SqliteQueue,
+    agent: String,
+    allowed_checks: Vec<String>,
+    proposals: HashMap<String, ChangeRequest>,
+}
+
+struct ToolError(Value);
+
+impl<'a> McpServer<'a> {
+    pub fn new(queue: &'a SqliteQueue, agent: String, allowed_checks: Vec<String>) -> Self {
+        Self {
+            queue,
+            agent,
+            allowed_checks,
+            proposals: HashMap::new(),
+        }
+    }
+
+    pub fn serve<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> anyhow::Result<()> {
+        for line in input.lines() {
+            let line = line.context("read mcp message")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => self.handle_message(&message),
+                Err(err) => Some(rpc_error(Value::Null, PARSE_ERROR, &err.to_string())),
+            };
+            if let Some(response) = response {
+                writeln!(output, "{response}").context("write mcp response")?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn handle_message(&mut self, message: &Value) -> Option<Value> {
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            return id.map(|id| rpc_error(id, INVALID_REQUEST, "missing method"));
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": params
+                    .get("protocolVersion")
+                    .and_then(Value::as_str)
+                    .unwrap_or(PROTOCOL_VERSION),
+                "capabilities": { "tools": { "listChanged": false } },
+                "serverInfo": { "name": "hyperion", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+            "tools/call" => self.call_tool(&params),
+            _ if method.starts_with("notifications/") => return None,
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method {method}"))),
+        };
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => rpc_error(id, code, &message),
+        })
+    }
+
+    fn call_tool(&mut self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params.get("name").and_then(Value::as_str).ok_or((
+            INVALID_PARAMS,
+            "tools/call requires a tool name".to_string(),
+        ))?;
+        let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+        let outcome = match name {
+            "read_assignment" => self.read_assignment(&arguments),
+            "read_file" => self.read_file(&arguments),
+            "propose_change" => self.propose_change(&arguments),
+            "run_check" => self.run_check(&arguments),
+            "submit" => self.submit(&arguments),
+            _ => return Err((INVALID_PARAMS, format!("unknown tool {name}"))),
+        };
+        info!(tool = name, ok = outcome.is_ok(), "mcp tool call");
+        let (payload, is_error) = match outcome {
+            Ok(payload) => (payload, false),
+            Err(ToolError(payload)) => (payload, true),
+        };
+        Ok(json!({
+            "content": [{ "type": "text", "text": payload.to_string() }],
+            "structuredContent": payload,
+            "isError": is_error,
+        }))
+    }
+
+    fn read_assignment(&self, arguments: &Value) -> Result<Value, ToolError> {
+        let task_id = required_str(arguments, "task_id")?;
+        let assignment = self
+            .queue
+            .task_assignment(task_id)
+            .map_err(tool_failure)?
+            .ok_or_else(|| tool_error(&format!("unknown task assignment {task_id}")))?;
+        let failures = self
+            .queue
+            .recent_task_failures(task_id, crate::prompt::RECENT_FAILURE_LIMIT)
+            .map_err(tool_failure)?;
+        Ok(json!({ "assignment": assignment, "recent_failures": failures }))
+    }
+
+    fn read_file(&self, arguments: &Value) -> Result<Value, ToolError> {
+        let path = required_str(arguments, "path")?;
+        if Path::new(path).is_absolute() || path.contains("..") {
+            return Err(tool_error(&format!(
+                "path must be relative to the repository and must not contain '..', got {path}"
+            )));
+        }
+        let contents =
+            fs::read_to_string(path).map_err(|err| tool_error(&format!("read {path}: {err}")))?;
+        let lines: Vec<&str> = contents.lines().collect();
+        let start = arguments
+            .get("start_line")
+            .and_then(Value::as_u64)
+            .unwrap_or(1)
+            .max(1) as usize;
+        let end = arguments
+            .get("end_line")
+            .and_then(Value::as_u64)
+            .map_or(lines.len(), |end| end as usize)
+            .min(lines.len())
+            .min(start + MAX_READ_LINES - 1);
+        let excerpt = if start <= end {
+            lines[start - 1..end].join("\n")
+        } else {
+            String::new()
+        };
+        Ok(json!({
+            "path": path,
+            "sha256": context::sha256_hex(contents.as_bytes()),
+            "total_lines": lines.len(),
+            "start_line": start,
+            "end_line": end,
+            "content": excerpt,
+        }))
+    }
+
+    fn propose_change(&mut self, arguments: &Value) -> Result<Value, ToolError> {
+        let raw = arguments
+            .get("change_request")
+            .cloned()
+            .ok_or_else(|| tool_error("missing required argument `change_request`"))?;
+        let mut request: ChangeRequest = serde_json::from_value(raw)
+            .map_err(|err| tool_error(&format!("change_request does not parse: {err}")))?;
+        if request.agent.trim().is_empty() {
+            request.agent = self.agent.clone();
+        }
+        for change in &mut request.changes {
+            if change.patch_hash.is_none() {
+                change.patch_hash = Some(context::sha256_hex(change.patch.as_bytes()));
+            }
+        }
+        let validation = validator::validate_change_request(&request);
+        let apply_checks = if validation.valid {
+            apply::check_change_request(&request)
+        } else {
+            Vec::new()
+        };
+        let accepted = validation.valid && apply_checks.iter().all(|check| check.ok);
+        let report = json!({
+            "task_id": request.task_id,
+            "accepted": accepted,
+            "validation_errors": validation.errors,
+            "apply_checks": apply_checks,
+        });
+        if accepted {
+            self.proposals.insert(request.task_id.clone(), request);
+            Ok(report)
+        } else {
+            Err(ToolError(report))
+        }
+    }
+
+    fn run_check(&self, arguments: &Value) -> Result<Value, ToolError> {
+        let command = required_str(arguments, "command")?;
+        runner::check_allowed(command, &self.allowed_checks).map_err(|err| tool_error(&err))?;
+        let output = runner::run_check(command).map_err(tool_failure)?;
+        let payload = serde_json::to_value(&output).map_err(|err| tool_failure(err.into()))?;
+        if output.success {
+            Ok(payload)
+        } else {
+            Err(ToolError(payload))
+        }
+    }
+
+    fn submit(&mut self, arguments: &Value) -> Result<Value, ToolError> {
+        let task_id = match arguments.get("task_id").and_then(Value::as_str) {
+            Some(task_id) => task_id.to_string(),
+            None if self.proposals.len() == 1 => self.proposals.keys().next().cloned().unwrap(),
+            None => {
+                return Err(tool_error(
+                    "submit requires `task_id` unless exactly one change has been proposed",
+                ))
+            }
+        };
+        let request = self.proposals.get(&task_id).cloned().ok_or_else(|| {
+            tool_error(&format!(
+                "no accepted proposal for {task_id}; call propose_change first"
+            ))
+        })?;
+        let queue_id = self.queue.enqueue(&request).map_err(tool_failure)?;
+        self.proposals.remove(&task_id);
+        let _ = self.queue.update_assignment(
+            &task_id,
+            AssignmentState::Enqueued,
+            &request.agent,
+            Some(queue_id),
+            None,
+        );
+        let _ = self.queue.log_event(
+            queue_id,
+            &task_id,
+            "info",
+            "submitted via mcp",
+            Some(&json!({ "agent": request.agent })),
+        );
+        Ok(json!({ "task_id": task_id, "queue_id": queue_id }))
+    }
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "read_assignment",
+            "description": "Read a task assignment (summary, file targets, instructions) and its recent failures.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "task_id": { "type": "string" } },
+                "required": ["task_id"]
+            }
+        },
+        {
+            "name": "read_file",
+            "description": "Read a repository file (optionally a line range) along with its sha256, usable as base_hash.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string" },
+                    "start_line": { "type": "integer", "minimum": 1 },
+                    "end_line": { "type": "integer", "minimum": 1 }
+                },
+                "required": ["path"]
+            }
+        },
+        {
+            "name": "propose_change",
+            "description": "Validate a ChangeRequest and dry-run its patches with `git apply --check`. Accepted proposals can be submitted.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "change_request": { "type": "object" } },
+                "required": ["change_request"]
+            }
+        },
+        {
+            "name": "run_check",
+            "description": "Run an allow-listed check command (see checks.allowed) and return its output.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "command": { "type": "string" } },
+                "required": ["command"]
+            }
+        },
+        {
+            "name": "submit",
+            "description": "Enqueue the accepted proposal for a task.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "task_id": { "type": "string" } }
+            }
+        }
+    ])
+}
+
+fn required_str<'v>(arguments: &'v Value, name: &str) -> Result<&'v str, ToolError> {
+    arguments
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| tool_error(&format!("missing required argument `{name}`")))
+}
+
+fn tool_error(message: &str) -> ToolError {
+    ToolError(json!({ "error": message }))
+}
+
+fn tool_failure(err: anyhow::Error) -> ToolError {
+    tool_error(&err.to_string())
+}
+
+fn rpc_error(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{TaskAssignment, TaskRequest};
+
+    fn call(server: &mut McpServer, id: i64, name: &str, arguments: Value) -> Value {
+        let response = server
+            .handle_message(&json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "tools/call",
+                "params": { "name": name, "arguments": arguments },
+            }))
+            .unwrap();
+        response["result"].clone()
+    }
+
+    #[test]
+    fn validates_proposals_and_submits_them_to_the_queue() {
+        let db = std::env::temp_dir().join(format!("hyperion-mcp-{}.db", std::process::id()));
+        let _ = fs::remove_file(&db);
+        let queue = SqliteQueue::open(&db).unwrap();
+        let assignment = TaskAssignment {
+            task_id: "MCP-1-1".to_string(),
+            parent_request_id: "MCP-1".to_string(),
+            summary: "Add a note".to_string(),
+            file_targets: vec!["notes.txt".to_string()],
+            instructions: vec!["Keep it short.".to_string()],
+        };
+        queue
+            .record_task_request(
+                &TaskRequest {
+                    request_id: "MCP-1".to_string(),
+                    summary: "Add a note".to_string(),
+                    requested_changes: Vec::new(),
+                },
+                std::slice::from_ref(&assignment),
+            )
+            .unwrap();
+        let mut server = McpServer::new(&queue, "mcp-agent".to_string(), vec!["true".to_string()]);
+
+        let init = server
+            .handle_message(
+                &json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+            )
+            .unwrap();
+        assert_eq!(init["result"]["protocolVersion"], PROTOCOL_VERSION);
+        assert!(server
+            .handle_message(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+            .is_none());
+        let tools = server
+            .handle_message(&json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}))
+            .unwrap();
+        assert_eq!(tools["result"]["tools"].as_array().unwrap().len(), 5);
+
+        let read = call(
+            &mut server,
+            3,
+            "read_assignment",
+            json!({"task_id": "MCP-1-1"}),
+        );
+        assert_eq!(
+            read["structuredContent"]["assignment"]["summary"],
+            "Add a note"
+        );
+
+        let rejected = call(
+            &mut server,
+            4,
+            "propose_change",
+            json!({"change_request": {
+                "task_id": "MCP-1-1",
+                "agent": "",
+                "changes": [{"path": "../escape.txt", "operation": "add", "patch": "+++ b/../escape.txt\n"}],
+                "checks": ["true"]
+            }}),
+        );
+        assert_eq!(rejected["isError"], true);
+        assert!(!rejected["structuredContent"]["validation_errors"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            call(&mut server, 5, "submit", json!({"task_id": "MCP-1-1"}))["isError"],
+            true
+        );
+
+        let path = format!("mcp-note-{}.txt", std::process::id());
+        let accepted = call(
+            &mut server,
+            6,
+            "propose_change",
+            json!({"change_request": {
+                "task_id": "MCP-1-1",
+                "agent": "",
+                "changes": [{
+                    "path": path,
+                    "operation": "add",
+                    "patch": format!("--- /dev/null\n+++ b/{path}\n@@ -0,0 +1 @@\n+hello\n")
+                }],
+                "checks": ["true"]
+            }}),
+        );
+        assert_eq!(accepted["isError"], false, "{accepted}");
+
+        let denied = call(&mut server, 7, "run_check", json!({"command": "rm -rf ."}));
+        assert_eq!(denied["isError"], true);
+        let ran = call(&mut server, 8, "run_check", json!({"command": "true"}));
+        assert_eq!(ran["structuredContent"]["success"], true);
+
+        let submitted = call(&mut server, 9, "submit", json!({}));
+        let queue_id = submitted["structuredContent"]["queue_id"].as_i64().unwrap();
+        let record = queue.get_record(queue_id).unwrap().unwrap();
+        assert_eq!(record.payload.agent, "mcp-agent");
+        let status = queue.request_status("MCP-1").unwrap().unwrap();
+        assert_eq!(status.assignments[0].state, AssignmentState::Enqueued);
+        let _ = fs::remove_file(&db);
+    }
+}
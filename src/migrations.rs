@@ -0,0 +1,435 @@
+use std::{error::Error as StdError, fmt};
+
+use anyhow::Context;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+
+use crate::models::{AppliedMigration, PendingMigration, SchemaStatus};
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    up: fn(&Connection) -> anyhow::Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "baseline",
+        up: baseline,
+    },
+    Migration {
+        version: 2,
+        name: "named_queues",
+        up: named_queues,
+    },
+    Migration {
+        version: 3,
+        name: "backfill_change_queue_task_id",
+        up: backfill_change_queue_task_id,
+    },
+];
+
+#[derive(Debug)]
+pub struct SchemaTooNew {
+    pub database: i64,
+    pub binary: i64,
+}
+
+impl fmt::Display for SchemaTooNew {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "database schema version {} is newer than this binary supports ({}); upgrade hyperion",
+            self.database, self.binary
+        )
+    }
+}
+
+impl StdError for SchemaTooNew {}
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+pub fn current_version(conn: &Connection) -> anyhow::Result<i64> {
+    ensure_table(conn)?;
+    let version: Option<i64> =
+        conn.query_row("SELECT MAX(version) FROM schema_migrations", [], |row| {
+            row.get(0)
+        })?;
+    Ok(version.unwrap_or(0))
+}
+
+pub fn status(conn: &Connection) -> anyhow::Result<SchemaStatus> {
+    let current_version = current_version(conn)?;
+    let mut stmt =
+        conn.prepare("SELECT version, name, applied_at FROM schema_migrations ORDER BY version")?;
+    let applied = stmt
+        .query_map([], |row| {
+            Ok(AppliedMigration {
+                version: row.get(0)?,
+                name: row.get(1)?,
+                applied_at: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let pending = MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > current_version)
+        .map(|migration| PendingMigration {
+            version: migration.version,
+            name: migration.name.to_string(),
+        })
+        .collect();
+    Ok(SchemaStatus {
+        current_version,
+        latest_version: latest_version(),
+        applied,
+        pending,
+    })
+}
+
+pub fn migrate(conn: &mut Connection) -> anyhow::Result<Vec<AppliedMigration>> {
+    ensure_supported(conn)?;
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let done: Option<i64> = tx
+            .query_row(
+                "SELECT version FROM schema_migrations WHERE version = ?1",
+                params![migration.version],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if done.is_some() {
+            continue;
+        }
+        (migration.up)(&tx).with_context(|| {
+            format!("apply migration {} ({})", migration.version, migration.name)
+        })?;
+        let applied_at: i64 = tx.query_row("SELECT strftime('%s','now')", [], |row| row.get(0))?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, ?2, ?3)",
+            params![migration.version, migration.name, applied_at],
+        )?;
+        tx.commit()?;
+        applied.push(AppliedMigration {
+            version: migration.version,
+            name: migration.name.to_string(),
+            applied_at,
+        });
+    }
+    Ok(applied)
+}
+
+pub fn ensure_supported(conn: &Connection) -> anyhow::Result<()> {
+    let database = current_version(conn)?;
+    let binary = latest_version();
+    if database > binary {
+        return Err(SchemaTooNew { database, binary }.into());
+    }
+    Ok(())
+}
+
+fn ensure_table(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        );",
+    )
+    .context("create schema_migrations")?;
+    Ok(())
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> anyhow::Result<bool> {
+    let found: Option<String> = conn
+        .query_row(
+            "SELECT name FROM pragma_table_info(?1) WHERE name = ?2",
+            params![table, column],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(found.is_some())
+}
+
+fn add_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> anyhow::Result<()> {
+    if !has_column(conn, table, column)? {
+        conn.execute_batch(&format!(
+            "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+        ))
+        .with_context(|| format!("add column {table}.{column}"))?;
+    }
+    Ok(())
+}
+
+fn baseline(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS change_queue (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            status TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            task_id TEXT,
+            payload_hash TEXT,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            leased_until INTEGER,
+            lease_owner TEXT,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+            updated_at INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+            cancel_requested_at INTEGER
+        );
+        CREATE TABLE IF NOT EXISTS dead_letters (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            queue_id INTEGER NOT NULL,
+            task_id TEXT NOT NULL,
+            agent TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            error TEXT,
+            failed_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        );
+        CREATE TABLE IF NOT EXISTS change_queue_logs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            queue_id INTEGER NOT NULL,
+            task_id TEXT NOT NULL,
+            level TEXT NOT NULL,
+            message TEXT NOT NULL,
+            details JSON,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        );
+        CREATE TABLE IF NOT EXISTS agent_sessions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            resume_id TEXT NOT NULL UNIQUE,
+            model TEXT NOT NULL,
+            allow_all_tools INTEGER NOT NULL DEFAULT 1,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+            last_used INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        );
+        CREATE TABLE IF NOT EXISTS task_requests (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            request_id TEXT NOT NULL UNIQUE,
+            summary TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+            updated_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        );
+        CREATE TABLE IF NOT EXISTS task_assignments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            task_id TEXT NOT NULL UNIQUE,
+            parent_request_id TEXT NOT NULL,
+            summary TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            state TEXT NOT NULL,
+            agent TEXT,
+            queue_id INTEGER,
+            error TEXT,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+            updated_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        );
+        CREATE TABLE IF NOT EXISTS workers (
+            worker_id TEXT PRIMARY KEY,
+            hostname TEXT NOT NULL,
+            pid INTEGER NOT NULL,
+            capabilities TEXT NOT NULL,
+            state TEXT NOT NULL,
+            current_queue_id INTEGER,
+            started_at INTEGER NOT NULL,
+            heartbeat_at INTEGER NOT NULL,
+            stopped_at INTEGER,
+            labels TEXT NOT NULL DEFAULT '[]'
+        );
+        CREATE TABLE IF NOT EXISTS runtime_control (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        );
+        CREATE TABLE IF NOT EXISTS queue_events (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            queue_id INTEGER,
+            task_id TEXT,
+            data JSON NOT NULL,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        );
+        CREATE TABLE IF NOT EXISTS file_modifications (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL,
+            event TEXT NOT NULL,
+            source TEXT NOT NULL,
+            details JSON,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        );",
+    )
+    .context("create base tables")?;
+    add_column(conn, "change_queue", "lease_owner", "TEXT")?;
+    if !has_column(conn, "change_queue", "updated_at")? {
+        add_column(
+            conn,
+            "change_queue",
+            "updated_at",
+            "INTEGER NOT NULL DEFAULT 0",
+        )?;
+        conn.execute("UPDATE change_queue SET updated_at = created_at", [])?;
+    }
+    add_column(conn, "change_queue", "task_id", "TEXT")?;
+    add_column(conn, "change_queue", "payload_hash", "TEXT")?;
+    add_column(conn, "change_queue", "cancel_requested_at", "INTEGER")?;
+    add_column(conn, "workers", "labels", "TEXT NOT NULL DEFAULT '[]'")?;
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_dead_letters_queue_id ON dead_letters(queue_id);
+         CREATE INDEX IF NOT EXISTS idx_dead_letters_failed_at ON dead_letters(failed_at);
+         CREATE INDEX IF NOT EXISTS idx_change_queue_status ON change_queue(status);
+         CREATE INDEX IF NOT EXISTS idx_change_queue_status_lease_id ON change_queue(status, leased_until, id);
+         CREATE INDEX IF NOT EXISTS idx_change_queue_lease ON change_queue(leased_until);
+         CREATE INDEX IF NOT EXISTS idx_change_queue_logs_queue_id ON change_queue_logs(queue_id);
+         CREATE INDEX IF NOT EXISTS idx_agent_sessions_last_used ON agent_sessions(last_used);
+         CREATE INDEX IF NOT EXISTS idx_file_modifications_created_at ON file_modifications(created_at);
+         CREATE INDEX IF NOT EXISTS idx_task_assignments_parent ON task_assignments(parent_request_id);
+         CREATE INDEX IF NOT EXISTS idx_task_requests_created_at ON task_requests(created_at);
+         CREATE INDEX IF NOT EXISTS idx_change_queue_payload_hash ON change_queue(payload_hash);",
+    )
+    .context("create indexes")?;
+    Ok(())
+}
+
+fn named_queues(conn: &Connection) -> anyhow::Result<()> {
+    for table in [
+        "change_queue",
+        "dead_letters",
+        "change_queue_logs",
+        "agent_sessions",
+        "task_requests",
+        "task_assignments",
+        "queue_events",
+        "file_modifications",
+    ] {
+        add_column(conn, table, "queue_name", "TEXT NOT NULL DEFAULT 'default'")?;
+    }
+    add_column(
+        conn,
+        "workers",
+        "queues",
+        "TEXT NOT NULL DEFAULT '[\"default\"]'",
+    )?;
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_change_queue_name_status ON change_queue(queue_name, status, leased_until, id);
+         CREATE INDEX IF NOT EXISTS idx_dead_letters_queue_name ON dead_letters(queue_name, failed_at);
+         CREATE INDEX IF NOT EXISTS idx_change_queue_logs_queue_name ON change_queue_logs(queue_name, created_at);
+         CREATE INDEX IF NOT EXISTS idx_queue_events_queue_name ON queue_events(queue_name, seq);
+         DROP INDEX IF EXISTS idx_change_queue_task_payload_hash;
+         CREATE UNIQUE INDEX IF NOT EXISTS idx_change_queue_name_task_payload_hash ON change_queue(queue_name, task_id, payload_hash);
+         UPDATE OR IGNORE runtime_control SET key = key || ':default'
+          WHERE key IN ('queue_mode', 'paused_agents');
+         DROP TRIGGER IF EXISTS trg_change_queue_insert_event;
+         DROP TRIGGER IF EXISTS trg_change_queue_status_event;
+         DROP TRIGGER IF EXISTS trg_change_queue_logs_event;
+         DROP TRIGGER IF EXISTS trg_file_modifications_event;
+         CREATE TRIGGER trg_change_queue_insert_event AFTER INSERT ON change_queue
+         BEGIN
+             INSERT INTO queue_events (queue_name, kind, queue_id, task_id, data)
+             VALUES (NEW.queue_name, 'status', NEW.id, NEW.task_id,
+                     json_object('from', NULL, 'to', NEW.status, 'attempts', NEW.attempts));
+         END;
+         CREATE TRIGGER trg_change_queue_status_event AFTER UPDATE OF status ON change_queue
+         WHEN OLD.status IS NOT NEW.status
+         BEGIN
+             INSERT INTO queue_events (queue_name, kind, queue_id, task_id, data)
+             VALUES (NEW.queue_name, 'status', NEW.id, NEW.task_id,
+                     json_object('from', OLD.status, 'to', NEW.status, 'attempts', NEW.attempts,
+                                 'lease_owner', NEW.lease_owner, 'last_error', NEW.last_error));
+         END;
+         CREATE TRIGGER trg_change_queue_logs_event AFTER INSERT ON change_queue_logs
+         BEGIN
+             INSERT INTO queue_events (queue_name, kind, queue_id, task_id, data)
+             VALUES (NEW.queue_name, 'log', NULLIF(NEW.queue_id, 0), NEW.task_id,
+                     json_object('level', NEW.level, 'message', NEW.message,
+                                 'details', json(NEW.details)));
+         END;
+         CREATE TRIGGER trg_file_modifications_event AFTER INSERT ON file_modifications
+         BEGIN
+             INSERT INTO queue_events (queue_name, kind, queue_id, task_id, data)
+             VALUES (NEW.queue_name, 'file', NULL, NULL,
+                     json_object('path', NEW.path, 'event', NEW.event, 'source', NEW.source,
+                                 'details', json(NEW.details)));
+         END;",
+    )
+    .context("namespace tables by queue")?;
+    Ok(())
+}
+
+fn backfill_change_queue_task_id(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute(
+        "UPDATE change_queue SET task_id = json_extract(payload, '$.task_id')
+         WHERE task_id IS NULL AND json_valid(payload)",
+        [],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrades_untracked_legacy_database_and_refuses_newer_schema() {
+        let db = std::env::temp_dir().join(format!("hyperion-migrate-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&db);
+        let mut conn = Connection::open(&db).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE change_queue (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                status TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                last_error TEXT,
+                leased_until INTEGER,
+                created_at INTEGER NOT NULL DEFAULT 1700000000
+            );
+            INSERT INTO change_queue (status, payload) VALUES ('pending', '{\"task_id\":\"OLD-1\"}');
+            CREATE TABLE runtime_control (key TEXT PRIMARY KEY, value TEXT NOT NULL, updated_at INTEGER);
+            INSERT INTO runtime_control (key, value) VALUES ('queue_mode', 'paused');",
+        )
+        .unwrap();
+        assert_eq!(current_version(&conn).unwrap(), 0);
+
+        let applied = migrate(&mut conn).unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        let (task_id, updated_at, queue_name): (String, i64, String) = conn
+            .query_row(
+                "SELECT task_id, updated_at, queue_name FROM change_queue",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            (task_id.as_str(), updated_at, queue_name.as_str()),
+            ("OLD-1", 1700000000, "default")
+        );
+        let mode: String = conn
+            .query_row(
+                "SELECT value FROM runtime_control WHERE key = 'queue_mode:default'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(mode, "paused");
+        assert!(migrate(&mut conn).unwrap().is_empty());
+        assert!(status(&conn).unwrap().pending.is_empty());
+
+        conn.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, 'future', 0)",
+            params![latest_version() + 1],
+        )
+        .unwrap();
+        let err = migrate(&mut conn).unwrap_err();
+        assert!(err.downcast_ref::<SchemaTooNew>().is_some());
+        let _ = std::fs::remove_file(&db);
+    }
+}
@@ -1,13 +1,19 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+pub const DEFAULT_QUEUE: &str = "default";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChangeRequest {
     pub task_id: String,
     pub agent: String,
     pub changes: Vec<ChangeOperation>,
     pub checks: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +38,75 @@ pub struct TaskAssignment {
     pub instructions: Vec<String>,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AssignmentState {
+    Pending,
+    Enqueued,
+    AgentFailed,
+    Invalid,
+    Cancelled,
+}
+
+impl AssignmentState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AssignmentState::Pending => "pending",
+            AssignmentState::Enqueued => "enqueued",
+            AssignmentState::AgentFailed => "agent_failed",
+            AssignmentState::Invalid => "invalid",
+            AssignmentState::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskAssignmentRecord {
+    pub task_id: String,
+    pub parent_request_id: String,
+    pub summary: String,
+    pub state: AssignmentState,
+    pub agent: Option<String>,
+    pub queue_id: Option<i64>,
+    pub queue_status: Option<QueueStatus>,
+    pub attempts: Option<i64>,
+    pub error: Option<String>,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestRollup {
+    Pending,
+    InProgress,
+    Applied,
+    PartiallyFailed,
+    Failed,
+    Cancelled,
+}
+
+impl RequestRollup {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestRollup::Pending => "pending",
+            RequestRollup::InProgress => "in_progress",
+            RequestRollup::Applied => "applied",
+            RequestRollup::PartiallyFailed => "partially_failed",
+            RequestRollup::Failed => "failed",
+            RequestRollup::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskRequestStatus {
+    pub request_id: String,
+    pub summary: String,
+    pub rollup: RequestRollup,
+    pub created_at: i64,
+    pub assignments: Vec<TaskAssignmentRecord>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValidationResult {
     pub valid: bool,
@@ -45,6 +120,8 @@ pub struct ChangeOperation {
     pub patch: String,
     #[serde(default)]
     pub patch_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_hash: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -58,6 +135,7 @@ pub enum OperationKind {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueueRecord {
     pub id: i64,
+    pub queue: String,
     pub status: QueueStatus,
     pub payload: ChangeRequest,
     pub attempts: i64,
@@ -109,6 +187,16 @@ pub struct FileModification {
     pub created_at: i64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueEvent {
+    pub seq: i64,
+    pub kind: String,
+    pub queue_id: Option<i64>,
+    pub task_id: Option<String>,
+    pub data: Value,
+    pub created_at: i64,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ValueEnum)]
 #[serde(rename_all = "snake_case")]
 pub enum QueueStatus {
@@ -116,6 +204,7 @@ pub enum QueueStatus {
     InProgress,
     Applied,
     Failed,
+    Cancelled,
 }
 
 impl QueueStatus {
@@ -125,8 +214,133 @@ impl QueueStatus {
             QueueStatus::InProgress => "in_progress",
             QueueStatus::Applied => "applied",
             QueueStatus::Failed => "failed",
+            QueueStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "outcome", content = "status")]
+pub enum CancelOutcome {
+    Cancelled,
+    Requested,
+    Finished(QueueStatus),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WorkerCapabilities {
+    pub run_checks: bool,
+    pub cargo: bool,
+    pub network: bool,
+    pub worktree: String,
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkerState {
+    Idle,
+    Busy,
+    Paused,
+    Stopped,
+}
+
+impl WorkerState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkerState::Idle => "idle",
+            WorkerState::Busy => "busy",
+            WorkerState::Paused => "paused",
+            WorkerState::Stopped => "stopped",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerRecord {
+    pub worker_id: String,
+    pub hostname: String,
+    pub pid: u32,
+    pub capabilities: WorkerCapabilities,
+    #[serde(default)]
+    pub queues: Vec<String>,
+    pub state: WorkerState,
+    pub current_queue_id: Option<i64>,
+    pub started_at: i64,
+    pub heartbeat_at: i64,
+    pub stopped_at: Option<i64>,
+}
+
+impl WorkerCapabilities {
+    pub fn advertised(&self) -> BTreeSet<String> {
+        let mut labels: BTreeSet<String> = self.labels.iter().cloned().collect();
+        if self.run_checks {
+            labels.insert("checks".to_string());
+        }
+        if self.cargo {
+            labels.insert("cargo".to_string());
+        }
+        if self.network {
+            labels.insert("network".to_string());
+        }
+        labels.insert(format!("worktree:{}", self.worktree));
+        labels
+    }
+}
+
+impl WorkerRecord {
+    pub fn is_dead(&self, now: i64, stale_secs: i64) -> bool {
+        self.state != WorkerState::Stopped && now - self.heartbeat_at > stale_secs
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrphanedLease {
+    pub worker_id: String,
+    pub queue_id: i64,
+    pub task_id: String,
+    pub leased_until: Option<i64>,
+    pub heartbeat_at: i64,
+    pub worker_state: WorkerState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssignmentCancellation {
+    pub task_id: String,
+    pub outcome: Option<CancelOutcome>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueMode {
+    #[default]
+    Running,
+    Paused,
+    Draining,
+}
+
+impl QueueMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueueMode::Running => "running",
+            QueueMode::Paused => "paused",
+            QueueMode::Draining => "draining",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Option<Self> {
+        match value {
+            "running" => Some(QueueMode::Running),
+            "paused" => Some(QueueMode::Paused),
+            "draining" => Some(QueueMode::Draining),
+            _ => None,
         }
     }
+
+    pub fn accepts_work(&self) -> bool {
+        *self == QueueMode::Running
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -135,12 +349,17 @@ pub struct StatusCounts {
     pub in_progress: usize,
     pub applied: usize,
     pub failed: usize,
+    #[serde(default)]
+    pub cancelled: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueueMetrics {
     pub window_seconds: i64,
+    pub queue: String,
     pub status_counts: StatusCounts,
+    #[serde(default)]
+    pub queues: BTreeMap<String, StatusCounts>,
     pub avg_dequeue_latency_ms: Option<f64>,
     pub avg_apply_duration_ms: Option<f64>,
     pub avg_poll_interval_ms: Option<f64>,
@@ -155,6 +374,27 @@ pub struct QueueMetrics {
     pub timestamp_skew_secs: Option<i64>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    pub applied_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingMigration {
+    pub version: i64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaStatus {
+    pub current_version: i64,
+    pub latest_version: i64,
+    pub applied: Vec<AppliedMigration>,
+    pub pending: Vec<PendingMigration>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WalCheckpointStats {
     pub checkpointed: i64,
@@ -166,7 +406,9 @@ impl Default for QueueMetrics {
     fn default() -> Self {
         Self {
             window_seconds: 60,
+            queue: DEFAULT_QUEUE.to_string(),
             status_counts: StatusCounts::default(),
+            queues: BTreeMap::new(),
             avg_dequeue_latency_ms: None,
             avg_apply_duration_ms: None,
             avg_poll_interval_ms: None,
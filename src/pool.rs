@@ -0,0 +1,203 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use tracing::{info, warn};
+
+use crate::models::WorkerCapabilities;
+use crate::queue::{SqliteQueue, CONTROL_DESIRED_WORKERS};
+use crate::worker::{self, WorkerConfig};
+
+pub const SCALE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkerSettings {
+    pub lease_seconds: u64,
+    pub poll_interval_ms: u64,
+    pub run_checks: bool,
+    pub max_attempts: i64,
+    pub capabilities: WorkerCapabilities,
+}
+
+struct PoolWorker {
+    index: usize,
+    running: Arc<AtomicBool>,
+    handle: JoinHandle<anyhow::Result<()>>,
+}
+
+pub struct WorkerPool {
+    queue: Arc<SqliteQueue>,
+    settings: WorkerSettings,
+    max_workers: usize,
+    active: Vec<PoolWorker>,
+    retiring: Vec<PoolWorker>,
+    size: Arc<AtomicUsize>,
+    restarts: u64,
+}
+
+impl WorkerPool {
+    pub fn new(queue: Arc<SqliteQueue>, settings: WorkerSettings, max_workers: usize) -> Self {
+        Self {
+            queue,
+            settings,
+            max_workers: max_workers.max(1),
+            active: Vec::new(),
+            retiring: Vec::new(),
+            size: Arc::new(AtomicUsize::new(0)),
+            restarts: 0,
+        }
+    }
+
+    pub fn size_handle(&self) -> Arc<AtomicUsize> {
+        self.size.clone()
+    }
+
+    pub fn max_workers(&self) -> usize {
+        self.max_workers
+    }
+
+    pub fn restarts(&self) -> u64 {
+        self.restarts
+    }
+
+    pub fn reconfigure(&mut self, settings: WorkerSettings, max_workers: usize) -> usize {
+        self.max_workers = max_workers.max(1);
+        let target = self.active.len();
+        if settings != self.settings {
+            self.settings = settings;
+            for worker in self.active.drain(..) {
+                worker.running.store(false, Ordering::SeqCst);
+                self.retiring.push(worker);
+            }
+            info!("worker settings changed; rolling workers");
+        }
+        self.scale_to(target)
+    }
+
+    pub fn restart_crashed(&mut self) -> usize {
+        let (crashed, active): (Vec<_>, Vec<_>) = self
+            .active
+            .drain(..)
+            .partition(|worker| worker.handle.is_finished());
+        self.active = active;
+        for worker in &crashed {
+            warn!(worker_index = worker.index, "restarting crashed worker");
+        }
+        for worker in crashed {
+            let index = worker.index;
+            match worker.handle.join() {
+                Ok(Err(err)) => eprintln!("worker-{index} failed: {err}"),
+                Err(err) => eprintln!("worker-{index} panicked: {err:?}"),
+                Ok(Ok(())) => {}
+            }
+            self.spawn(index);
+            self.restarts += 1;
+        }
+        self.active.len()
+    }
+
+    pub fn scale_to(&mut self, target: usize) -> usize {
+        let target = target.clamp(1, self.max_workers);
+        while self.active.len() < target {
+            let index = (1..)
+                .find(|index| self.active.iter().all(|worker| worker.index != *index))
+                .unwrap_or(1);
+            self.spawn(index);
+        }
+        while self.active.len() > target {
+            if let Some(worker) = self.active.pop() {
+                worker.running.store(false, Ordering::SeqCst);
+                info!(worker_index = worker.index, "retiring worker");
+                self.retiring.push(worker);
+            }
+        }
+        self.reap();
+        self.size.store(self.active.len(), Ordering::SeqCst);
+        self.active.len()
+    }
+
+    pub fn shutdown(mut self) {
+        for worker in self.active.drain(..) {
+            worker.running.store(false, Ordering::SeqCst);
+            self.retiring.push(worker);
+        }
+        self.size.store(0, Ordering::SeqCst);
+        for worker in self.retiring.drain(..) {
+            match worker.handle.join() {
+                Ok(Err(err)) => eprintln!("worker-{} failed: {err}", worker.index),
+                Err(err) => eprintln!("worker thread failed: {err:?}"),
+                Ok(Ok(())) => {}
+            }
+        }
+    }
+
+    fn spawn(&mut self, index: usize) {
+        let running = Arc::new(AtomicBool::new(true));
+        let signal = running.clone();
+        let queue = self.queue.clone();
+        let settings = self.settings.clone();
+        let handle = thread::spawn(move || {
+            worker::run_worker_with_signal(
+                queue.as_ref(),
+                WorkerConfig {
+                    worker_id: format!("worker-{index}"),
+                    lease_seconds: settings.lease_seconds,
+                    poll_interval_ms: settings.poll_interval_ms,
+                    run_checks: settings.run_checks,
+                    max_attempts: settings.max_attempts,
+                    capabilities: settings.capabilities,
+                },
+                signal,
+            )
+        });
+        self.active.push(PoolWorker {
+            index,
+            running,
+            handle,
+        });
+    }
+
+    pub fn reap(&mut self) {
+        let mut still_running = Vec::new();
+        for worker in self.retiring.drain(..) {
+            if worker.handle.is_finished() {
+                if let Ok(Err(err)) = worker.handle.join() {
+                    eprintln!("worker-{} failed: {err}", worker.index);
+                }
+            } else {
+                still_running.push(worker);
+            }
+        }
+        self.retiring = still_running;
+    }
+}
+
+pub fn supervise(
+    mut pool: WorkerPool,
+    initial: usize,
+    running: Arc<AtomicBool>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let queue = pool.queue.clone();
+        let mut target = pool.scale_to(initial);
+        let _ = queue.set_control(CONTROL_DESIRED_WORKERS, &target.to_string());
+        while running.load(Ordering::SeqCst) {
+            if let Ok(Some(desired)) = queue.desired_workers() {
+                let desired = desired.clamp(1, pool.max_workers());
+                if desired != target {
+                    target = pool.scale_to(desired);
+                    info!(workers = target, "worker pool rescaled");
+                }
+            }
+            pool.restart_crashed();
+            pool.reap();
+            thread::sleep(SCALE_POLL_INTERVAL);
+        }
+        pool.shutdown();
+    })
+}
@@ -0,0 +1,239 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::context::ContextAssembler;
+use crate::models::TaskAssignment;
+use crate::queue::SqliteQueue;
+
+pub const DEFAULT_PROMPT_DIR: &str = "prompts";
+pub const RECENT_FAILURE_LIMIT: usize = 3;
+
+pub const BUILTIN_TEMPLATE: &str =
+    "You are {{agent_name}}. Produce a JSON change request only, no prose.
+
+Task ID: {{task_id}}
+Summary: {{summary}}
+Files: {{files}}
+Instructions:
+- {{instructions}}
+
+Return a single JSON object with fields: task_id, agent, changes (array), checks (array).
+Each change must include: path, operation (add/update/delete), patch (diff or full replacement).
+Include at least one check in the checks array.
+Each change may include base_hash: the sha256 listed below for the file as you read it, so stale patches are rejected.
+
+Repository context (git HEAD {{git_head}}):
+{{file_contents}}
+";
+
+pub const BUILTIN_POLICY_RULES: &str = "- Paths must be relative and must not contain '..'.
+- Update patches must mention both `--- a/<path>` and `+++ b/<path>`; add patches need `+++ b/<path>`, delete patches need `--- a/<path>`.
+- `patch_hash` is the SHA-256 of the patch text; omit it to let Hyperion compute it.";
+
+const TEMPLATE_SUFFIX: &str = ".prompt.md";
+
+#[derive(Debug, Clone)]
+pub struct PromptTemplates {
+    dir: PathBuf,
+    pub context: ContextAssembler,
+}
+
+#[derive(Debug, Clone)]
+pub struct SelectedTemplate {
+    pub source: String,
+    pub body: String,
+}
+
+pub struct PromptInputs<'a> {
+    pub assignment: &'a TaskAssignment,
+    pub agent_name: &'a str,
+    pub file_contents: String,
+    pub git_head: String,
+    pub policy_rules: String,
+    pub recent_failures: String,
+}
+
+impl PromptTemplates {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            context: ContextAssembler::new("."),
+        }
+    }
+
+    pub fn select(&self, task_id: &str, harness: &str) -> anyhow::Result<SelectedTemplate> {
+        if let Some(path) = self.task_prefix_template(task_id)? {
+            return Self::read(&path);
+        }
+        for name in [format!("harness-{harness}"), "default".to_string()] {
+            let path = self.dir.join(format!("{name}{TEMPLATE_SUFFIX}"));
+            if path.is_file() {
+                return Self::read(&path);
+            }
+        }
+        Ok(SelectedTemplate {
+            source: "builtin".to_string(),
+            body: BUILTIN_TEMPLATE.to_string(),
+        })
+    }
+
+    pub fn policy_rules(&self) -> anyhow::Result<String> {
+        let path = self.dir.join("policy.md");
+        if path.is_file() {
+            let rules = fs::read_to_string(&path)
+                .with_context(|| format!("read policy rules {}", path.display()))?;
+            Ok(rules.trim_end().to_string())
+        } else {
+            Ok(BUILTIN_POLICY_RULES.to_string())
+        }
+    }
+
+    fn task_prefix_template(&self, task_id: &str) -> anyhow::Result<Option<PathBuf>> {
+        if !self.dir.is_dir() {
+            return Ok(None);
+        }
+        let mut best: Option<(usize, PathBuf)> = None;
+        let entries = fs::read_dir(&self.dir)
+            .with_context(|| format!("read prompt templates {}", self.dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let Some(prefix) = name
+                .strip_prefix("task-")
+                .and_then(|rest| rest.strip_suffix(TEMPLATE_SUFFIX))
+            else {
+                continue;
+            };
+            if !prefix.is_empty()
+                && task_id.starts_with(prefix)
+                && best.as_ref().is_none_or(|(len, _)| prefix.len() > *len)
+            {
+                best = Some((prefix.len(), path.clone()));
+            }
+        }
+        Ok(best.map(|(_, path)| path))
+    }
+
+    fn read(path: &Path) -> anyhow::Result<SelectedTemplate> {
+        let body = fs::read_to_string(path)
+            .with_context(|| format!("read prompt template {}", path.display()))?;
+        Ok(SelectedTemplate {
+            source: path.display().to_string(),
+            body,
+        })
+    }
+}
+
+pub fn render(template: &str, inputs: &PromptInputs) -> String {
+    let assignment = inputs.assignment;
+    template
+        .replace("{{agent_name}}", inputs.agent_name)
+        .replace("{{task_id}}", &assignment.task_id)
+        .replace("{{parent_request_id}}", &assignment.parent_request_id)
+        .replace("{{summary}}", &assignment.summary)
+        .replace("{{files}}", &assignment.file_targets.join(", "))
+        .replace("{{instructions}}", &assignment.instructions.join("\n- "))
+        .replace("{{file_contents}}", &inputs.file_contents)
+        .replace("{{git_head}}", &inputs.git_head)
+        .replace("{{policy_rules}}", &inputs.policy_rules)
+        .replace("{{recent_failures}}", &inputs.recent_failures)
+}
+
+pub fn build_prompt(
+    queue: &SqliteQueue,
+    templates: &PromptTemplates,
+    assignment: &TaskAssignment,
+    agent_name: &str,
+    harness: &str,
+) -> anyhow::Result<String> {
+    let template = templates.select(&assignment.task_id, harness)?;
+    let context = templates.context.assemble(assignment);
+    let inputs = PromptInputs {
+        assignment,
+        agent_name,
+        file_contents: context.render_files(),
+        git_head: context.git_head.unwrap_or_else(|| "unknown".to_string()),
+        policy_rules: templates.policy_rules()?,
+        recent_failures: recent_failures(queue, &assignment.task_id)?,
+    };
+    Ok(render(&template.body, &inputs))
+}
+
+fn recent_failures(queue: &SqliteQueue, task_id: &str) -> anyhow::Result<String> {
+    let failures = queue.recent_task_failures(task_id, RECENT_FAILURE_LIMIT)?;
+    if failures.is_empty() {
+        Ok("(none)".to_string())
+    } else {
+        Ok(failures
+            .iter()
+            .map(|failure| format!("- {failure}"))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assignment(task_id: &str) -> TaskAssignment {
+        TaskAssignment {
+            task_id: task_id.to_string(),
+            parent_request_id: "REQ-7".to_string(),
+            summary: "Tighten parsing".to_string(),
+            file_targets: vec!["src/lib.rs".to_string()],
+            instructions: vec!["Keep it small.".to_string(), "Add a check.".to_string()],
+        }
+    }
+
+    #[test]
+    fn selects_longest_task_prefix_then_harness_then_builtin() {
+        let dir = std::env::temp_dir().join(format!("hyperion-prompts-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("task-REQ.prompt.md"), "short").unwrap();
+        fs::write(dir.join("task-REQ-7.prompt.md"), "long").unwrap();
+        fs::write(dir.join("harness-copilot.prompt.md"), "copilot").unwrap();
+        let templates = PromptTemplates::new(&dir);
+
+        assert_eq!(templates.select("REQ-7-1", "copilot").unwrap().body, "long");
+        assert_eq!(
+            templates.select("REQ-8-1", "copilot").unwrap().body,
+            "short"
+        );
+        assert_eq!(
+            templates.select("ENG-1", "copilot").unwrap().body,
+            "copilot"
+        );
+        assert_eq!(
+            templates.select("ENG-1", "other").unwrap().source,
+            "builtin"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn builtin_template_renders_assignment_fields() {
+        let assignment = assignment("REQ-7-1");
+        let rendered = render(
+            BUILTIN_TEMPLATE,
+            &PromptInputs {
+                assignment: &assignment,
+                agent_name: "agent-2",
+                file_contents: String::new(),
+                git_head: String::new(),
+                policy_rules: String::new(),
+                recent_failures: String::new(),
+            },
+        );
+        assert!(rendered.starts_with("You are agent-2."));
+        assert!(rendered.contains("Task ID: REQ-7-1\nSummary: Tighten parsing\nFiles: src/lib.rs"));
+        assert!(rendered.contains("- Keep it small.\n- Add a check.\n"));
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, BTreeSet, HashSet},
     path::{Path, PathBuf},
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
@@ -11,107 +11,94 @@ use rusqlite::{
 use serde_json::{json, Value};
 use sha2::{Digest, Sha256};
 
+use crate::migrations;
 use crate::models::{
-    AgentSession, ChangeQueueLog, ChangeRequest, DeadLetterRecord, FileModification, QueueMetrics,
-    QueueRecord, QueueStatus, StatusCounts, WalCheckpointStats,
+    AgentSession, AppliedMigration, AssignmentCancellation, AssignmentState, CancelOutcome,
+    ChangeQueueLog, ChangeRequest, DeadLetterRecord, FileModification, OrphanedLease, QueueEvent,
+    QueueMetrics, QueueMode, QueueRecord, QueueStatus, RequestRollup, SchemaStatus, StatusCounts,
+    TaskAssignment, TaskAssignmentRecord, TaskRequest, TaskRequestStatus, WalCheckpointStats,
+    WorkerCapabilities, WorkerRecord, WorkerState, DEFAULT_QUEUE,
 };
 
 pub const DEFAULT_APPLIED_RETENTION_SECS: i64 = 7 * 24 * 60 * 60;
 pub const DEFAULT_DEADLETTER_RETENTION_SECS: i64 = 30 * 24 * 60 * 60;
 pub const DEFAULT_DEDUP_WINDOW_SECS: i64 = 24 * 60 * 60;
+pub const CONTROL_DESIRED_WORKERS: &str = "desired_workers";
+pub const CONTROL_QUEUE_MODE: &str = "queue_mode";
+pub const CONTROL_PAUSED_AGENTS: &str = "paused_agents";
+pub const WORKER_STALE_SECS: i64 = 120;
 
 pub struct SqliteQueue {
     path: PathBuf,
+    name: String,
+    subscriptions: Vec<String>,
+    max_in_flight: BTreeMap<String, usize>,
 }
 
 impl SqliteQueue {
     pub fn open(path: &Path) -> anyhow::Result<Self> {
         let queue = SqliteQueue {
             path: path.to_path_buf(),
+            name: DEFAULT_QUEUE.to_string(),
+            subscriptions: vec![DEFAULT_QUEUE.to_string()],
+            max_in_flight: BTreeMap::new(),
         };
         queue.initialize_schema()?;
         Ok(queue)
     }
 
+    pub fn scoped(mut self, name: &str) -> anyhow::Result<Self> {
+        validate_queue_name(name)?;
+        self.name = name.to_string();
+        self.subscriptions = vec![name.to_string()];
+        Ok(self)
+    }
+
+    pub fn subscribed(mut self, names: &[String]) -> anyhow::Result<Self> {
+        if names.is_empty() {
+            return Ok(self);
+        }
+        for name in names {
+            validate_queue_name(name)?;
+        }
+        self.subscriptions = names.to_vec();
+        Ok(self)
+    }
+
+    pub fn with_max_in_flight(mut self, limits: BTreeMap<String, usize>) -> Self {
+        self.max_in_flight = limits;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn subscriptions(&self) -> &[String] {
+        &self.subscriptions
+    }
+
+    fn scoped_key(&self, key: &str) -> String {
+        format!("{key}:{}", self.name)
+    }
+
     fn initialize_schema(&self) -> anyhow::Result<()> {
-        let conn = Connection::open(&self.path).context("open sqlite queue")?;
-        Self::configure_pragmas(&conn)?;
-        conn.execute_batch(
-            "CREATE TABLE IF NOT EXISTS change_queue (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                status TEXT NOT NULL,
-                payload TEXT NOT NULL,
-                task_id TEXT,
-                payload_hash TEXT,
-                attempts INTEGER NOT NULL DEFAULT 0,
-                last_error TEXT,
-                leased_until INTEGER,
-                lease_owner TEXT,
-                created_at INTEGER NOT NULL DEFAULT (strftime('%s','now')),
-                updated_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
-            );
-            CREATE TABLE IF NOT EXISTS dead_letters (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                queue_id INTEGER NOT NULL,
-                task_id TEXT NOT NULL,
-                agent TEXT NOT NULL,
-                payload TEXT NOT NULL,
-                error TEXT,
-                failed_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
-            );
-            CREATE TABLE IF NOT EXISTS change_queue_logs (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                queue_id INTEGER NOT NULL,
-                task_id TEXT NOT NULL,
-                level TEXT NOT NULL,
-                message TEXT NOT NULL,
-                details JSON,
-                created_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
-            );
-            CREATE TABLE IF NOT EXISTS agent_sessions (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                resume_id TEXT NOT NULL UNIQUE,
-                model TEXT NOT NULL,
-                allow_all_tools INTEGER NOT NULL DEFAULT 1,
-                created_at INTEGER NOT NULL DEFAULT (strftime('%s','now')),
-                last_used INTEGER NOT NULL DEFAULT (strftime('%s','now'))
-            );
-            CREATE TABLE IF NOT EXISTS file_modifications (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                path TEXT NOT NULL,
-                event TEXT NOT NULL,
-                source TEXT NOT NULL,
-                details JSON,
-                created_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
-            );",
-        )
-        .context("create base tables")?;
-        conn.execute_batch(
-            "CREATE INDEX IF NOT EXISTS idx_dead_letters_queue_id ON dead_letters(queue_id);
-             CREATE INDEX IF NOT EXISTS idx_dead_letters_failed_at ON dead_letters(failed_at);
-             CREATE INDEX IF NOT EXISTS idx_change_queue_status ON change_queue(status);
-             CREATE INDEX IF NOT EXISTS idx_change_queue_status_lease_id ON change_queue(status, leased_until, id);
-             CREATE INDEX IF NOT EXISTS idx_change_queue_lease ON change_queue(leased_until);
-             CREATE INDEX IF NOT EXISTS idx_change_queue_logs_queue_id ON change_queue_logs(queue_id);
-             CREATE INDEX IF NOT EXISTS idx_agent_sessions_last_used ON agent_sessions(last_used);
-             CREATE INDEX IF NOT EXISTS idx_file_modifications_created_at ON file_modifications(created_at);",
-        )
-        .context("create indexes")?;
-        Self::try_add_column(&conn, "lease_owner TEXT")?;
-        Self::try_add_column(
-            &conn,
-            "updated_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))",
-        )?;
-        Self::try_add_column(&conn, "task_id TEXT")?;
-        Self::try_add_column(&conn, "payload_hash TEXT")?;
-        conn.execute_batch(
-            "CREATE UNIQUE INDEX IF NOT EXISTS idx_change_queue_task_payload_hash ON change_queue(task_id, payload_hash);
-             CREATE INDEX IF NOT EXISTS idx_change_queue_payload_hash ON change_queue(payload_hash);",
-        )
-        .context("create dedupe indexes")?;
+        Self::migrate(&self.path)?;
         Ok(())
     }
 
+    pub fn migrate(path: &Path) -> anyhow::Result<Vec<AppliedMigration>> {
+        let mut conn = Connection::open(path).context("open sqlite queue")?;
+        Self::configure_pragmas(&conn)?;
+        migrations::migrate(&mut conn)
+    }
+
+    pub fn schema_status(path: &Path) -> anyhow::Result<SchemaStatus> {
+        let conn = Connection::open(path).context("open sqlite queue")?;
+        Self::configure_pragmas(&conn)?;
+        migrations::status(&conn)
+    }
+
     fn configure_pragmas(conn: &Connection) -> anyhow::Result<()> {
         conn.pragma_update(None, "journal_mode", "WAL")
             .context("enable WAL")?;
@@ -122,6 +109,10 @@ impl SqliteQueue {
         Ok(())
     }
 
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     fn connection(&self) -> anyhow::Result<Connection> {
         let conn = Connection::open(&self.path).context("open sqlite queue")?;
         Self::configure_pragmas(&conn)?;
@@ -137,12 +128,13 @@ impl SqliteQueue {
     }
 
     fn find_live_duplicate(
+        &self,
         conn: &Connection,
         task_id: &str,
         payload_hash: &str,
     ) -> anyhow::Result<Option<i64>> {
         let mut stmt = conn.prepare(
-            "SELECT id FROM change_queue WHERE task_id = ?1 AND payload_hash = ?2 AND status IN (?3, ?4) LIMIT 1",
+            "SELECT id FROM change_queue WHERE task_id = ?1 AND payload_hash = ?2 AND status IN (?3, ?4) AND queue_name = ?5 LIMIT 1",
         )?;
         let row = stmt
             .query_row(
@@ -150,7 +142,8 @@ impl SqliteQueue {
                     task_id,
                     payload_hash,
                     QueueStatus::Pending.as_str(),
-                    QueueStatus::InProgress.as_str()
+                    QueueStatus::InProgress.as_str(),
+                    self.name
                 ],
                 |row| row.get(0),
             )
@@ -163,7 +156,7 @@ impl SqliteQueue {
         let payload_hash = Self::change_request_hash(request)?;
         let conn = self.connection()?;
         if let Some(existing_id) =
-            Self::find_live_duplicate(&conn, &request.task_id, &payload_hash)?
+            self.find_live_duplicate(&conn, &request.task_id, &payload_hash)?
         {
             let _ = self.log_event(
                 existing_id,
@@ -181,13 +174,14 @@ impl SqliteQueue {
             );
         }
         conn.execute(
-            "INSERT INTO change_queue (status, payload, task_id, payload_hash, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            "INSERT INTO change_queue (status, payload, task_id, payload_hash, updated_at, queue_name) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
             params![
                 QueueStatus::Pending.as_str(),
                 payload,
                 request.task_id,
                 payload_hash,
-                now_epoch()?
+                now_epoch()?,
+                self.name
             ],
         )?;
         Ok(conn.last_insert_rowid())
@@ -204,15 +198,17 @@ impl SqliteQueue {
         let details_json = details.map(|value| value.to_string());
         let conn = self.connection()?;
         conn.execute(
-            "INSERT INTO change_queue_logs (queue_id, task_id, level, message, details, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT INTO change_queue_logs (queue_id, task_id, level, message, details, created_at, queue_name)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6,
+                     COALESCE((SELECT queue_name FROM change_queue WHERE id = ?1), ?7))",
             params![
                 queue_id,
                 task_id,
                 level,
                 message,
                 details_json,
-                now_epoch()?
+                now_epoch()?,
+                self.name
             ],
         )?;
         Ok(())
@@ -228,9 +224,9 @@ impl SqliteQueue {
         let details_json = details.map(|value| value.to_string());
         let conn = self.connection()?;
         conn.execute(
-            "INSERT INTO file_modifications (path, event, source, details, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![path, event, source, details_json, now_epoch()?],
+            "INSERT INTO file_modifications (path, event, source, details, created_at, queue_name)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![path, event, source, details_json, now_epoch()?, self.name],
         )?;
         Ok(())
     }
@@ -239,22 +235,77 @@ impl SqliteQueue {
         &self,
         lease_duration: Duration,
         lease_owner: &str,
+    ) -> anyhow::Result<Option<QueueRecord>> {
+        self.dequeue_matching(lease_duration, lease_owner, false)
+    }
+
+    pub fn dequeue_active(
+        &self,
+        lease_duration: Duration,
+        lease_owner: &str,
+    ) -> anyhow::Result<Option<QueueRecord>> {
+        self.dequeue_matching(lease_duration, lease_owner, true)
+    }
+
+    fn dequeue_matching(
+        &self,
+        lease_duration: Duration,
+        lease_owner: &str,
+        running_only: bool,
     ) -> anyhow::Result<Option<QueueRecord>> {
         let now = now_epoch()?;
         let lease_until = now + lease_duration.as_secs() as i64;
         let mut conn = self.connection()?;
         let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        tx.execute(
+            "UPDATE change_queue SET status = ?1, leased_until = NULL, lease_owner = NULL, updated_at = ?2
+             WHERE status = ?3 AND leased_until < ?2 AND cancel_requested_at IS NOT NULL",
+            params![
+                QueueStatus::Cancelled.as_str(),
+                now,
+                QueueStatus::InProgress.as_str()
+            ],
+        )?;
         let row = tx
                 .query_row(
-                    "SELECT id, status, payload, attempts, last_error, leased_until, lease_owner, created_at
+                    "SELECT id, status, payload, attempts, last_error, leased_until, lease_owner, created_at, queue_name
                  FROM change_queue
-                 WHERE status = ?1 OR (status = ?2 AND leased_until < ?3)
+                 WHERE (status = ?1 OR (status = ?2 AND leased_until < ?3))
+                   AND cancel_requested_at IS NULL
+                   AND queue_name IN (SELECT value FROM json_each(?6))
+                   AND (?10 = 0 OR COALESCE(
+                       (SELECT value FROM runtime_control WHERE key = ?7 || ':' || change_queue.queue_name),
+                       ?8) = ?8)
+                   AND (json_extract(?9, '$.' || change_queue.queue_name) IS NULL
+                        OR json_extract(?9, '$.' || change_queue.queue_name) > (
+                            SELECT COUNT(*) FROM change_queue active
+                            WHERE active.queue_name = change_queue.queue_name
+                              AND active.status = ?2 AND active.leased_until >= ?3))
+                   AND NOT EXISTS (
+                       SELECT 1 FROM json_each(change_queue.payload, '$.requires') AS required
+                       WHERE required.value NOT IN (
+                           SELECT value FROM json_each(COALESCE(
+                               (SELECT labels FROM workers WHERE worker_id = ?5), '[]'))
+                       )
+                   )
+                   AND COALESCE(json_extract(payload, '$.agent'), '') NOT IN (
+                       SELECT value FROM json_each(COALESCE(
+                           (SELECT value FROM runtime_control
+                             WHERE key = ?4 || ':' || change_queue.queue_name), '[]'))
+                   )
                  ORDER BY id
                  LIMIT 1",
                     params![
                         QueueStatus::Pending.as_str(),
                         QueueStatus::InProgress.as_str(),
-                        now
+                        now,
+                        CONTROL_PAUSED_AGENTS,
+                        lease_owner,
+                        serde_json::to_string(&self.subscriptions)?,
+                        CONTROL_QUEUE_MODE,
+                        QueueMode::Running.as_str(),
+                        serde_json::to_string(&self.max_in_flight)?,
+                        running_only
                     ],
                     Self::queue_record_from_row,
                 )
@@ -300,15 +351,17 @@ impl SqliteQueue {
         if let Some(payload) = payload {
             if let Ok(request) = serde_json::from_str::<ChangeRequest>(&payload) {
                 let _ = conn.execute(
-                    "INSERT INTO dead_letters (queue_id, task_id, agent, payload, error, failed_at)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    "INSERT INTO dead_letters (queue_id, task_id, agent, payload, error, failed_at, queue_name)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6,
+                             COALESCE((SELECT queue_name FROM change_queue WHERE id = ?1), ?7))",
                     params![
                         id,
                         request.task_id,
                         request.agent,
                         payload,
                         error,
-                        now_epoch()?
+                        now_epoch()?,
+                        self.name
                     ],
                 );
             }
@@ -317,14 +370,163 @@ impl SqliteQueue {
     }
 
     pub fn mark_retry(&self, id: i64, error: Option<String>) -> anyhow::Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "UPDATE change_queue
+             SET status = CASE WHEN cancel_requested_at IS NULL THEN ?1 ELSE ?5 END,
+                 last_error = ?2, leased_until = NULL, lease_owner = NULL, updated_at = ?3
+             WHERE id = ?4",
+            params![
+                QueueStatus::Pending.as_str(),
+                error,
+                now_epoch()?,
+                id,
+                QueueStatus::Cancelled.as_str()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn requeue(&self, id: i64) -> anyhow::Result<bool> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let updated = tx.execute(
+            "UPDATE change_queue SET status = ?1, attempts = 0, last_error = NULL, leased_until = NULL, lease_owner = NULL, cancel_requested_at = NULL, updated_at = ?2
+             WHERE id = ?3 AND status = ?4",
+            params![
+                QueueStatus::Pending.as_str(),
+                now_epoch()?,
+                id,
+                QueueStatus::Failed.as_str()
+            ],
+        )?;
+        if updated > 0 {
+            tx.execute("DELETE FROM dead_letters WHERE queue_id = ?1", params![id])?;
+        }
+        tx.commit()?;
+        Ok(updated > 0)
+    }
+
+    pub fn cancel(&self, id: i64) -> anyhow::Result<Option<CancelOutcome>> {
+        let now = now_epoch()?;
+        let mut conn = self.connection()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let row: Option<(String, Option<i64>)> = tx
+            .query_row(
+                "SELECT status, leased_until FROM change_queue WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((status, leased_until)) = row else {
+            return Ok(None);
+        };
+        let status = QueueStatus::from_string(&status)?;
+        let outcome = match status {
+            QueueStatus::InProgress if leased_until.is_some_and(|until| until >= now) => {
+                tx.execute(
+                    "UPDATE change_queue SET cancel_requested_at = ?1 WHERE id = ?2",
+                    params![now, id],
+                )?;
+                CancelOutcome::Requested
+            }
+            QueueStatus::Pending | QueueStatus::InProgress => {
+                tx.execute(
+                    "UPDATE change_queue SET status = ?1, last_error = ?2, leased_until = NULL, lease_owner = NULL, cancel_requested_at = ?3, updated_at = ?3 WHERE id = ?4",
+                    params![
+                        QueueStatus::Cancelled.as_str(),
+                        "cancelled by operator",
+                        now,
+                        id
+                    ],
+                )?;
+                CancelOutcome::Cancelled
+            }
+            finished => CancelOutcome::Finished(finished),
+        };
+        tx.commit()?;
+        Ok(Some(outcome))
+    }
+
+    pub fn cancel_requested(&self, id: i64) -> anyhow::Result<bool> {
+        let conn = self.connection()?;
+        let requested: Option<Option<i64>> = conn
+            .query_row(
+                "SELECT cancel_requested_at FROM change_queue WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(requested.flatten().is_some())
+    }
+
+    pub fn mark_cancelled(&self, id: i64, reason: &str) -> anyhow::Result<()> {
         let conn = self.connection()?;
         conn.execute(
             "UPDATE change_queue SET status = ?1, last_error = ?2, leased_until = NULL, lease_owner = NULL, updated_at = ?3 WHERE id = ?4",
-            params![QueueStatus::Pending.as_str(), error, now_epoch()?, id],
+            params![QueueStatus::Cancelled.as_str(), reason, now_epoch()?, id],
         )?;
         Ok(())
     }
 
+    pub fn cancel_request(
+        &self,
+        request_id: &str,
+    ) -> anyhow::Result<Option<Vec<AssignmentCancellation>>> {
+        let Some(status) = self.request_status(request_id)? else {
+            return Ok(None);
+        };
+        let mut outcomes = Vec::new();
+        for assignment in status.assignments {
+            let outcome = match (assignment.queue_id, assignment.state) {
+                (Some(queue_id), _) => self.cancel(queue_id)?,
+                (None, AssignmentState::Pending) => {
+                    self.update_assignment(
+                        &assignment.task_id,
+                        AssignmentState::Cancelled,
+                        assignment.agent.as_deref().unwrap_or_default(),
+                        None,
+                        Some("cancelled by operator".to_string()),
+                    )?;
+                    Some(CancelOutcome::Cancelled)
+                }
+                (None, _) => None,
+            };
+            outcomes.push(AssignmentCancellation {
+                task_id: assignment.task_id,
+                outcome,
+            });
+        }
+        Ok(Some(outcomes))
+    }
+
+    pub fn latest_record_for_task(&self, task_id: &str) -> anyhow::Result<Option<i64>> {
+        let conn = self.connection()?;
+        let id = conn
+            .query_row(
+                "SELECT id FROM change_queue WHERE task_id = ?1 AND queue_name = ?2 ORDER BY id DESC LIMIT 1",
+                params![task_id, self.name],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(id)
+    }
+
+    pub fn assignment_state(&self, task_id: &str) -> anyhow::Result<Option<AssignmentState>> {
+        let conn = self.connection()?;
+        let state: Option<String> = conn
+            .query_row(
+                "SELECT state FROM task_assignments WHERE task_id = ?1",
+                params![task_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(state
+            .as_deref()
+            .map(AssignmentState::from_string)
+            .transpose()?)
+    }
+
     pub fn mark_applied(&self, id: i64) -> anyhow::Result<()> {
         let conn = self.connection()?;
         conn.execute(
@@ -338,11 +540,13 @@ impl SqliteQueue {
         let threshold = now_epoch()? - ttl_seconds;
         let conn = self.connection()?;
         let deleted = conn.execute(
-            "DELETE FROM change_queue WHERE status IN (?1, ?2) AND updated_at < ?3",
+            "DELETE FROM change_queue WHERE status IN (?1, ?2, ?3) AND updated_at < ?4 AND queue_name = ?5",
             params![
                 QueueStatus::Applied.as_str(),
                 QueueStatus::Failed.as_str(),
-                threshold
+                QueueStatus::Cancelled.as_str(),
+                threshold,
+                self.name
             ],
         )?;
         let _ = self.log_event(
@@ -362,12 +566,44 @@ impl SqliteQueue {
     pub fn list(&self, status: QueueStatus) -> anyhow::Result<Vec<QueueRecord>> {
         let conn = self.connection()?;
         let mut stmt = conn.prepare(
-            "SELECT id, status, payload, attempts, last_error, leased_until, lease_owner, created_at
+            "SELECT id, status, payload, attempts, last_error, leased_until, lease_owner, created_at, queue_name
              FROM change_queue
-             WHERE status = ?1
+             WHERE status = ?1 AND queue_name = ?2
              ORDER BY id",
         )?;
-        let mut rows = stmt.query(params![status.as_str()])?;
+        let mut rows = stmt.query(params![status.as_str(), self.name])?;
+        let mut records = Vec::new();
+        while let Some(row) = rows.next()? {
+            records.push(Self::queue_record_from_row(row)?);
+        }
+        Ok(records)
+    }
+
+    pub fn list_filtered(
+        &self,
+        status: Option<QueueStatus>,
+        since: Option<i64>,
+        task_id: Option<&str>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<QueueRecord>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, status, payload, attempts, last_error, leased_until, lease_owner, created_at, queue_name
+             FROM change_queue
+             WHERE (?1 IS NULL OR status = ?1)
+               AND (?2 IS NULL OR created_at >= ?2)
+               AND (?3 IS NULL OR task_id = ?3)
+               AND queue_name = ?5
+             ORDER BY id
+             LIMIT ?4",
+        )?;
+        let mut rows = stmt.query(params![
+            status.map(|status| status.as_str()),
+            since,
+            task_id,
+            limit as i64,
+            self.name
+        ])?;
         let mut records = Vec::new();
         while let Some(row) = rows.next()? {
             records.push(Self::queue_record_from_row(row)?);
@@ -380,30 +616,63 @@ impl SqliteQueue {
         let mut stmt = conn.prepare(
             "SELECT id, queue_id, task_id, level, message, details, created_at
              FROM change_queue_logs
+             WHERE queue_name = ?2
              ORDER BY created_at DESC
              LIMIT ?1",
         )?;
-        let mut rows = stmt.query(params![limit as i64])?;
+        let mut rows = stmt.query(params![limit as i64, self.name])?;
         let mut logs = Vec::new();
         while let Some(row) = rows.next()? {
-            let id: i64 = row.get(0)?;
-            let queue_id: i64 = row.get(1)?;
-            let task_id: String = row.get(2)?;
-            let level: String = row.get(3)?;
-            let message: String = row.get(4)?;
-            let details_str: Option<String> = row.get(5)?;
-            let created_at: i64 = row.get(6)?;
-            let details = details_str.and_then(|text| serde_json::from_str::<Value>(&text).ok());
-            logs.push(ChangeQueueLog {
-                id,
-                queue_id,
-                task_id,
-                level,
-                message,
-                details,
-                created_at,
+            logs.push(Self::log_from_row(row)?);
+        }
+        Ok(logs)
+    }
+
+    pub fn events_after(&self, seq: i64, limit: usize) -> anyhow::Result<Vec<QueueEvent>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT seq, kind, queue_id, task_id, data, created_at
+             FROM queue_events
+             WHERE seq > ?1 AND queue_name = ?3
+             ORDER BY seq
+             LIMIT ?2",
+        )?;
+        let mut rows = stmt.query(params![seq, limit as i64, self.name])?;
+        let mut events = Vec::new();
+        while let Some(row) = rows.next()? {
+            let data: String = row.get(4)?;
+            events.push(QueueEvent {
+                seq: row.get(0)?,
+                kind: row.get(1)?,
+                queue_id: row.get(2)?,
+                task_id: row.get(3)?,
+                data: serde_json::from_str(&data).unwrap_or(Value::Null),
+                created_at: row.get(5)?,
             });
         }
+        Ok(events)
+    }
+
+    pub fn latest_event_seq(&self) -> anyhow::Result<i64> {
+        let conn = self.connection()?;
+        let seq: Option<i64> =
+            conn.query_row("SELECT MAX(seq) FROM queue_events", [], |row| row.get(0))?;
+        Ok(seq.unwrap_or(0))
+    }
+
+    pub fn logs_for_record(&self, queue_id: i64) -> anyhow::Result<Vec<ChangeQueueLog>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, queue_id, task_id, level, message, details, created_at
+             FROM change_queue_logs
+             WHERE queue_id = ?1
+             ORDER BY id",
+        )?;
+        let mut rows = stmt.query(params![queue_id])?;
+        let mut logs = Vec::new();
+        while let Some(row) = rows.next()? {
+            logs.push(Self::log_from_row(row)?);
+        }
         Ok(logs)
     }
 
@@ -412,10 +681,11 @@ impl SqliteQueue {
         let mut stmt = conn.prepare(
             "SELECT id, path, event, source, details, created_at
              FROM file_modifications
+             WHERE queue_name = ?2
              ORDER BY created_at DESC
              LIMIT ?1",
         )?;
-        let mut rows = stmt.query(params![limit as i64])?;
+        let mut rows = stmt.query(params![limit as i64, self.name])?;
         let mut events = Vec::new();
         while let Some(row) = rows.next()? {
             let id: i64 = row.get(0)?;
@@ -446,8 +716,8 @@ impl SqliteQueue {
         let now = now_epoch()?;
         let conn = self.connection()?;
         conn.execute(
-            "INSERT INTO agent_sessions (resume_id, model, allow_all_tools, created_at, last_used)
-             VALUES (?1, ?2, ?3, ?4, ?5)
+            "INSERT INTO agent_sessions (resume_id, model, allow_all_tools, created_at, last_used, queue_name)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
              ON CONFLICT(resume_id) DO UPDATE SET
                model = excluded.model,
                allow_all_tools = excluded.allow_all_tools,
@@ -457,7 +727,8 @@ impl SqliteQueue {
                 model,
                 if allow_all_tools { 1 } else { 0 },
                 now,
-                now
+                now,
+                self.name
             ],
         )?;
         let mut stmt = conn.prepare(
@@ -474,11 +745,12 @@ impl SqliteQueue {
         let mut stmt = conn.prepare(
             "SELECT id, resume_id, model, allow_all_tools, created_at, last_used
              FROM agent_sessions
+             WHERE queue_name = ?1
              ORDER BY last_used DESC
              LIMIT 1",
         )?;
         let row = stmt
-            .query_row([], Self::agent_session_from_row)
+            .query_row(params![self.name], Self::agent_session_from_row)
             .optional()?;
         Ok(row)
     }
@@ -488,9 +760,10 @@ impl SqliteQueue {
         let mut stmt = conn.prepare(
             "SELECT id, resume_id, model, allow_all_tools, created_at, last_used
              FROM agent_sessions
+             WHERE queue_name = ?1
              ORDER BY created_at DESC",
         )?;
-        let mut rows = stmt.query([])?;
+        let mut rows = stmt.query(params![self.name])?;
         let mut sessions = Vec::new();
         while let Some(row) = rows.next()? {
             sessions.push(Self::agent_session_from_row(row)?);
@@ -507,15 +780,467 @@ impl SqliteQueue {
         Ok(())
     }
 
+    pub fn record_task_request(
+        &self,
+        request: &TaskRequest,
+        assignments: &[TaskAssignment],
+    ) -> anyhow::Result<()> {
+        let now = now_epoch()?;
+        let payload = serde_json::to_string(request).context("serialize task request")?;
+        let mut conn = self.connection()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        tx.execute(
+            "INSERT INTO task_requests (request_id, summary, payload, created_at, updated_at, queue_name)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(request_id) DO UPDATE SET
+               summary = excluded.summary,
+               payload = excluded.payload,
+               updated_at = excluded.updated_at",
+            params![
+                request.request_id,
+                request.summary,
+                payload,
+                now,
+                now,
+                self.name
+            ],
+        )?;
+        for assignment in assignments {
+            let payload = serde_json::to_string(assignment).context("serialize task assignment")?;
+            tx.execute(
+                "INSERT INTO task_assignments (task_id, parent_request_id, summary, payload, state, created_at, updated_at, queue_name)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 ON CONFLICT(task_id) DO UPDATE SET
+                   parent_request_id = excluded.parent_request_id,
+                   summary = excluded.summary,
+                   payload = excluded.payload,
+                   state = excluded.state,
+                   agent = NULL,
+                   queue_id = NULL,
+                   error = NULL,
+                   updated_at = excluded.updated_at",
+                params![
+                    assignment.task_id,
+                    assignment.parent_request_id,
+                    assignment.summary,
+                    payload,
+                    AssignmentState::Pending.as_str(),
+                    now,
+                    now,
+                    self.name
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn update_assignment(
+        &self,
+        task_id: &str,
+        state: AssignmentState,
+        agent: &str,
+        queue_id: Option<i64>,
+        error: Option<String>,
+    ) -> anyhow::Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "UPDATE task_assignments SET state = ?1, agent = ?2, queue_id = ?3, error = ?4, updated_at = ?5 WHERE task_id = ?6",
+            params![state.as_str(), agent, queue_id, error, now_epoch()?, task_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn request_status(&self, request_id: &str) -> anyhow::Result<Option<TaskRequestStatus>> {
+        let conn = self.connection()?;
+        let row = conn
+            .query_row(
+                "SELECT request_id, summary, created_at FROM task_requests WHERE request_id = ?1",
+                params![request_id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, i64>(2)?,
+                    ))
+                },
+            )
+            .optional()?;
+        match row {
+            Some((request_id, summary, created_at)) => Ok(Some(Self::build_request_status(
+                &conn, request_id, summary, created_at,
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn recent_request_statuses(&self, limit: usize) -> anyhow::Result<Vec<TaskRequestStatus>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT request_id, summary, created_at
+             FROM task_requests
+             WHERE queue_name = ?2
+             ORDER BY created_at DESC, id DESC
+             LIMIT ?1",
+        )?;
+        let rows = stmt
+            .query_map(params![limit as i64, self.name], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut statuses = Vec::with_capacity(rows.len());
+        for (request_id, summary, created_at) in rows {
+            statuses.push(Self::build_request_status(
+                &conn, request_id, summary, created_at,
+            )?);
+        }
+        Ok(statuses)
+    }
+
+    fn build_request_status(
+        conn: &Connection,
+        request_id: String,
+        summary: String,
+        created_at: i64,
+    ) -> anyhow::Result<TaskRequestStatus> {
+        let mut stmt = conn.prepare(
+            "SELECT a.task_id, a.parent_request_id, a.summary, a.state, a.agent, a.queue_id,
+                    COALESCE(q.status, CASE
+                        WHEN a.queue_id IS NULL THEN NULL
+                        WHEN EXISTS (SELECT 1 FROM dead_letters d WHERE d.queue_id = a.queue_id) THEN 'failed'
+                        ELSE 'applied'
+                    END),
+                    q.attempts, COALESCE(q.last_error, a.error), a.updated_at
+             FROM task_assignments a
+             LEFT JOIN change_queue q ON q.id = a.queue_id
+             WHERE a.parent_request_id = ?1
+             ORDER BY a.id",
+        )?;
+        let mut rows = stmt.query(params![request_id])?;
+        let mut assignments = Vec::new();
+        while let Some(row) = rows.next()? {
+            let state: String = row.get(3)?;
+            let queue_status: Option<String> = row.get(6)?;
+            assignments.push(TaskAssignmentRecord {
+                task_id: row.get(0)?,
+                parent_request_id: row.get(1)?,
+                summary: row.get(2)?,
+                state: AssignmentState::from_string(&state)?,
+                agent: row.get(4)?,
+                queue_id: row.get(5)?,
+                queue_status: queue_status
+                    .as_deref()
+                    .map(QueueStatus::from_string)
+                    .transpose()?,
+                attempts: row.get(7)?,
+                error: row.get(8)?,
+                updated_at: row.get(9)?,
+            });
+        }
+        Ok(TaskRequestStatus {
+            request_id,
+            summary,
+            rollup: rollup_assignments(&assignments),
+            created_at,
+            assignments,
+        })
+    }
+
+    pub fn task_assignment(&self, task_id: &str) -> anyhow::Result<Option<TaskAssignment>> {
+        let conn = self.connection()?;
+        let payload: Option<String> = conn
+            .query_row(
+                "SELECT payload FROM task_assignments WHERE task_id = ?1",
+                params![task_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        payload
+            .map(|payload| serde_json::from_str(&payload).context("parse task assignment"))
+            .transpose()
+    }
+
+    pub fn recent_task_failures(&self, task_id: &str, limit: usize) -> anyhow::Result<Vec<String>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT error FROM (
+                SELECT last_error AS error, updated_at AS at FROM change_queue
+                 WHERE task_id = ?1 AND last_error IS NOT NULL
+                UNION ALL
+                SELECT error, failed_at FROM dead_letters
+                 WHERE task_id = ?1 AND error IS NOT NULL
+                UNION ALL
+                SELECT error, updated_at FROM task_assignments
+                 WHERE task_id = ?1 AND error IS NOT NULL
+             )
+             GROUP BY error
+             ORDER BY MAX(at) DESC
+             LIMIT ?2",
+        )?;
+        let failures = stmt
+            .query_map(params![task_id, limit as i64], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(failures)
+    }
+
+    pub fn set_control(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT INTO runtime_control (key, value, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+            params![key, value, now_epoch()?],
+        )?;
+        Ok(())
+    }
+
+    pub fn control_value(&self, key: &str) -> anyhow::Result<Option<String>> {
+        let conn = self.connection()?;
+        let value = conn
+            .query_row(
+                "SELECT value FROM runtime_control WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value)
+    }
+
+    pub fn desired_workers(&self) -> anyhow::Result<Option<usize>> {
+        Ok(self
+            .control_value(CONTROL_DESIRED_WORKERS)?
+            .and_then(|value| value.parse().ok()))
+    }
+
+    pub fn queue_mode(&self) -> anyhow::Result<QueueMode> {
+        self.mode_of(&self.name)
+    }
+
+    fn mode_of(&self, name: &str) -> anyhow::Result<QueueMode> {
+        Ok(self
+            .control_value(&format!("{CONTROL_QUEUE_MODE}:{name}"))?
+            .and_then(|value| QueueMode::from_string(&value))
+            .unwrap_or_default())
+    }
+
+    pub fn subscription_mode(&self) -> anyhow::Result<QueueMode> {
+        let mut mode = QueueMode::Paused;
+        for name in &self.subscriptions {
+            match self.mode_of(name)? {
+                QueueMode::Running => return Ok(QueueMode::Running),
+                QueueMode::Draining => mode = QueueMode::Draining,
+                QueueMode::Paused => {}
+            }
+        }
+        Ok(mode)
+    }
+
+    pub fn set_queue_mode(&self, mode: QueueMode) -> anyhow::Result<()> {
+        self.set_control(&self.scoped_key(CONTROL_QUEUE_MODE), mode.as_str())?;
+        self.log_event(
+            0,
+            "control",
+            "info",
+            "queue mode changed",
+            Some(&json!({ "mode": mode.as_str() })),
+        )
+    }
+
+    pub fn paused_agents(&self) -> anyhow::Result<BTreeSet<String>> {
+        Ok(self
+            .control_value(&self.scoped_key(CONTROL_PAUSED_AGENTS))?
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default())
+    }
+
+    pub fn set_agent_paused(&self, agent: &str, paused: bool) -> anyhow::Result<()> {
+        let mut agents = self.paused_agents()?;
+        let changed = if paused {
+            agents.insert(agent.to_string())
+        } else {
+            agents.remove(agent)
+        };
+        if changed {
+            self.set_control(
+                &self.scoped_key(CONTROL_PAUSED_AGENTS),
+                &serde_json::to_string(&agents)?,
+            )?;
+            self.log_event(
+                0,
+                "control",
+                "info",
+                if paused {
+                    "agent paused"
+                } else {
+                    "agent resumed"
+                },
+                Some(&json!({ "agent": agent })),
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn in_flight_count(&self) -> anyhow::Result<usize> {
+        let conn = self.connection()?;
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM change_queue WHERE status = ?1 AND leased_until >= ?2 AND queue_name = ?3",
+            params![QueueStatus::InProgress.as_str(), now_epoch()?, self.name],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
+    pub fn register_worker(
+        &self,
+        worker_id: &str,
+        hostname: &str,
+        capabilities: &WorkerCapabilities,
+    ) -> anyhow::Result<()> {
+        let now = now_epoch()?;
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT INTO workers (worker_id, hostname, pid, capabilities, labels, queues, state, current_queue_id, started_at, heartbeat_at, stopped_at)
+             VALUES (?1, ?2, ?3, ?4, ?7, ?8, ?5, NULL, ?6, ?6, NULL)
+             ON CONFLICT(worker_id) DO UPDATE SET
+                 hostname = excluded.hostname, pid = excluded.pid, capabilities = excluded.capabilities,
+                 labels = excluded.labels, queues = excluded.queues,
+                 state = excluded.state, current_queue_id = NULL, started_at = excluded.started_at,
+                 heartbeat_at = excluded.heartbeat_at, stopped_at = NULL",
+            params![
+                worker_id,
+                hostname,
+                std::process::id(),
+                serde_json::to_string(capabilities)?,
+                WorkerState::Idle.as_str(),
+                now,
+                serde_json::to_string(&capabilities.advertised())?,
+                serde_json::to_string(&self.subscriptions)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn worker_heartbeat(
+        &self,
+        worker_id: &str,
+        state: WorkerState,
+        current_queue_id: Option<i64>,
+    ) -> anyhow::Result<()> {
+        let now = now_epoch()?;
+        let conn = self.connection()?;
+        conn.execute(
+            "UPDATE workers SET state = ?1, current_queue_id = ?2, heartbeat_at = ?3,
+                 stopped_at = CASE WHEN ?1 = ?4 THEN ?3 ELSE NULL END
+             WHERE worker_id = ?5",
+            params![
+                state.as_str(),
+                current_queue_id,
+                now,
+                WorkerState::Stopped.as_str(),
+                worker_id
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn list_workers(&self) -> anyhow::Result<Vec<WorkerRecord>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT worker_id, hostname, pid, capabilities, state, current_queue_id, started_at, heartbeat_at, stopped_at, queues
+             FROM workers
+             ORDER BY worker_id",
+        )?;
+        let workers = stmt
+            .query_map([], Self::worker_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(workers)
+    }
+
+    pub fn orphaned_leases(&self, stale_secs: i64) -> anyhow::Result<Vec<OrphanedLease>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT w.worker_id, q.id, q.task_id, q.leased_until, w.heartbeat_at, w.state
+             FROM change_queue q
+             JOIN workers w ON w.worker_id = q.lease_owner
+             WHERE q.status = ?1 AND (w.state = ?2 OR w.heartbeat_at < ?3)
+             ORDER BY q.id",
+        )?;
+        let leases = stmt
+            .query_map(
+                params![
+                    QueueStatus::InProgress.as_str(),
+                    WorkerState::Stopped.as_str(),
+                    now_epoch()? - stale_secs
+                ],
+                |row| {
+                    let state: String = row.get(5)?;
+                    Ok(OrphanedLease {
+                        worker_id: row.get(0)?,
+                        queue_id: row.get(1)?,
+                        task_id: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                        leased_until: row.get(3)?,
+                        heartbeat_at: row.get(4)?,
+                        worker_state: WorkerState::from_string(&state)?,
+                    })
+                },
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(leases)
+    }
+
+    pub fn unroutable(&self) -> anyhow::Result<Vec<QueueRecord>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, status, payload, attempts, last_error, leased_until, lease_owner, created_at, queue_name
+             FROM change_queue q
+             WHERE q.status = ?1 AND q.queue_name = ?2
+               AND json_array_length(q.payload, '$.requires') > 0
+               AND NOT EXISTS (
+                   SELECT 1 FROM workers w
+                   WHERE q.queue_name IN (SELECT value FROM json_each(w.queues))
+                     AND NOT EXISTS (
+                       SELECT 1 FROM json_each(q.payload, '$.requires') AS required
+                       WHERE required.value NOT IN (SELECT value FROM json_each(w.labels))
+                   )
+               )
+             ORDER BY q.id",
+        )?;
+        let records = stmt
+            .query_map(
+                params![QueueStatus::Pending.as_str(), self.name],
+                Self::queue_record_from_row,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(records)
+    }
+
+    pub fn get_record(&self, id: i64) -> anyhow::Result<Option<QueueRecord>> {
+        let conn = self.connection()?;
+        let record = conn
+            .query_row(
+                "SELECT id, status, payload, attempts, last_error, leased_until, lease_owner, created_at, queue_name
+                 FROM change_queue
+                 WHERE id = ?1",
+                params![id],
+                Self::queue_record_from_row,
+            )
+            .optional()?;
+        Ok(record)
+    }
+
     pub fn recent_records(&self, limit: usize) -> anyhow::Result<Vec<QueueRecord>> {
         let conn = self.connection()?;
         let mut stmt = conn.prepare(
-            "SELECT id, status, payload, attempts, last_error, leased_until, lease_owner, created_at
+            "SELECT id, status, payload, attempts, last_error, leased_until, lease_owner, created_at, queue_name
              FROM change_queue
+             WHERE queue_name = ?2
              ORDER BY id DESC
              LIMIT ?1",
         )?;
-        let mut rows = stmt.query(params![limit as i64])?;
+        let mut rows = stmt.query(params![limit as i64, self.name])?;
         let mut records = Vec::new();
         while let Some(row) = rows.next()? {
             records.push(Self::queue_record_from_row(row)?);
@@ -525,8 +1250,11 @@ impl SqliteQueue {
 
     pub fn dead_letter_count(&self) -> anyhow::Result<i64> {
         let conn = self.connection()?;
-        let count: i64 =
-            conn.query_row("SELECT COUNT(*) FROM dead_letters", [], |row| row.get(0))?;
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM dead_letters WHERE queue_name = ?1",
+            params![self.name],
+            |row| row.get(0),
+        )?;
         Ok(count)
     }
 
@@ -535,9 +1263,10 @@ impl SqliteQueue {
         let mut stmt = conn.prepare(
             "SELECT id, queue_id, task_id, agent, payload, error, failed_at
              FROM dead_letters
+             WHERE queue_name = ?1
              ORDER BY failed_at DESC",
         )?;
-        let mut rows = stmt.query([])?;
+        let mut rows = stmt.query(params![self.name])?;
         let mut records = Vec::new();
         while let Some(row) = rows.next()? {
             let id: i64 = row.get(0)?;
@@ -564,8 +1293,8 @@ impl SqliteQueue {
     pub fn count_dedup_hits_since(&self, since: i64) -> anyhow::Result<i64> {
         let conn = self.connection()?;
         let count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM change_queue_logs WHERE message = 'duplicate change request' AND created_at >= ?1",
-            params![since],
+            "SELECT COUNT(*) FROM change_queue_logs WHERE message = 'duplicate change request' AND created_at >= ?1 AND queue_name = ?2",
+            params![since, self.name],
             |row| row.get(0),
         )?;
         Ok(count)
@@ -575,22 +1304,24 @@ impl SqliteQueue {
         let conn = self.connection()?;
         let row = conn
             .query_row(
-                "SELECT MAX(created_at) FROM change_queue_logs WHERE message = 'cleanup'",
-                [],
-                |row| row.get(0),
+                "SELECT MAX(created_at) FROM change_queue_logs WHERE message = 'cleanup' AND queue_name = ?1",
+                params![self.name],
+                |row| row.get::<_, Option<i64>>(0),
             )
             .optional()?;
-        Ok(row)
+        Ok(row.flatten())
     }
 
     pub fn max_updated_timestamp(&self) -> anyhow::Result<Option<i64>> {
         let conn = self.connection()?;
         let row = conn
-            .query_row("SELECT MAX(updated_at) FROM change_queue", [], |row| {
-                row.get(0)
-            })
+            .query_row(
+                "SELECT MAX(updated_at) FROM change_queue WHERE queue_name = ?1",
+                params![self.name],
+                |row| row.get::<_, Option<i64>>(0),
+            )
             .optional()?;
-        Ok(row)
+        Ok(row.flatten())
     }
 
     pub fn wal_checkpoint_status(&self) -> anyhow::Result<WalCheckpointStats> {
@@ -624,14 +1355,15 @@ impl SqliteQueue {
         let in_progress = self.list(QueueStatus::InProgress)?.len();
         let applied = self.list(QueueStatus::Applied)?.len();
         let failed = self.list(QueueStatus::Failed)?.len();
+        let cancelled = self.list(QueueStatus::Cancelled)?.len();
 
         let mut stmt = conn.prepare(
             "SELECT message, details
              FROM change_queue_logs
-             WHERE created_at >= ?1
+             WHERE created_at >= ?1 AND queue_name = ?2
              ORDER BY created_at DESC",
         )?;
-        let mut rows = stmt.query(params![since])?;
+        let mut rows = stmt.query(params![since, self.name])?;
         let mut dequeue_latency_total = 0.0;
         let mut dequeue_samples = 0usize;
         let mut poll_total = 0.0;
@@ -720,12 +1452,15 @@ impl SqliteQueue {
 
         Ok(QueueMetrics {
             window_seconds: window,
+            queue: self.name.clone(),
             status_counts: StatusCounts {
                 pending,
                 in_progress,
                 applied,
                 failed,
+                cancelled,
             },
+            queues: self.status_counts_by_queue()?,
             avg_dequeue_latency_ms: avg_dequeue_latency,
             avg_apply_duration_ms: avg_apply_duration,
             avg_poll_interval_ms: avg_poll_interval,
@@ -741,13 +1476,40 @@ impl SqliteQueue {
         })
     }
 
+    pub fn status_counts_by_queue(&self) -> anyhow::Result<BTreeMap<String, StatusCounts>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT queue_name, status, COUNT(*) FROM change_queue GROUP BY queue_name, status",
+        )?;
+        let mut rows = stmt.query([])?;
+        let mut queues: BTreeMap<String, StatusCounts> = BTreeMap::new();
+        while let Some(row) = rows.next()? {
+            let name: String = row.get(0)?;
+            let status: String = row.get(1)?;
+            let count = row.get::<_, i64>(2)? as usize;
+            let counts = queues.entry(name).or_default();
+            match QueueStatus::from_string(&status)? {
+                QueueStatus::Pending => counts.pending = count,
+                QueueStatus::InProgress => counts.in_progress = count,
+                QueueStatus::Applied => counts.applied = count,
+                QueueStatus::Failed => counts.failed = count,
+                QueueStatus::Cancelled => counts.cancelled = count,
+            }
+        }
+        for name in &self.subscriptions {
+            queues.entry(name.clone()).or_default();
+        }
+        queues.entry(self.name.clone()).or_default();
+        Ok(queues)
+    }
+
     #[allow(dead_code)]
     pub fn purge_applied(&self, ttl_secs: i64) -> anyhow::Result<usize> {
         let threshold = now_epoch()? - ttl_secs;
         let conn = self.connection()?;
         let deleted = conn.execute(
-            "DELETE FROM change_queue WHERE status = ?1 AND updated_at < ?2",
-            params![QueueStatus::Applied.as_str(), threshold],
+            "DELETE FROM change_queue WHERE status = ?1 AND updated_at < ?2 AND queue_name = ?3",
+            params![QueueStatus::Applied.as_str(), threshold, self.name],
         )?;
         Ok(deleted)
     }
@@ -757,8 +1519,8 @@ impl SqliteQueue {
         let threshold = now_epoch()? - ttl_secs;
         let conn = self.connection()?;
         let deleted = conn.execute(
-            "DELETE FROM dead_letters WHERE failed_at < ?1",
-            params![threshold],
+            "DELETE FROM dead_letters WHERE failed_at < ?1 AND queue_name = ?2",
+            params![threshold, self.name],
         )?;
         Ok(deleted)
     }
@@ -767,8 +1529,8 @@ impl SqliteQueue {
         let threshold = now_epoch()? - ttl_secs;
         let conn = self.connection()?;
         let count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM change_queue WHERE status = ?1 AND updated_at < ?2",
-            params![QueueStatus::Applied.as_str(), threshold],
+            "SELECT COUNT(*) FROM change_queue WHERE status = ?1 AND updated_at < ?2 AND queue_name = ?3",
+            params![QueueStatus::Applied.as_str(), threshold, self.name],
             |row| row.get(0),
         )?;
         Ok(count)
@@ -778,8 +1540,8 @@ impl SqliteQueue {
         let threshold = now_epoch()? - ttl_secs;
         let conn = self.connection()?;
         let count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM dead_letters WHERE failed_at < ?1",
-            params![threshold],
+            "SELECT COUNT(*) FROM dead_letters WHERE failed_at < ?1 AND queue_name = ?2",
+            params![threshold, self.name],
             |row| row.get(0),
         )?;
         Ok(count)
@@ -794,6 +1556,13 @@ impl SqliteQueue {
 
     pub fn verify_schema(&self) -> anyhow::Result<()> {
         let conn = self.connection()?;
+        let version = migrations::current_version(&conn)?;
+        let expected = migrations::latest_version();
+        if version != expected {
+            anyhow::bail!(
+                "schema version {version} does not match expected version {expected}; run `hyperion db migrate`"
+            );
+        }
         let mut stmt = conn.prepare("PRAGMA table_info(change_queue)")?;
         let mut columns = HashSet::new();
         let mut rows = stmt.query([])?;
@@ -864,10 +1633,12 @@ impl SqliteQueue {
         let leased_until: Option<i64> = row.get(5)?;
         let lease_owner: Option<String> = row.get(6)?;
         let created_at: i64 = row.get(7)?;
+        let queue: String = row.get(8)?;
         let payload: ChangeRequest = serde_json::from_str(&payload)
             .map_err(|err| Error::FromSqlConversionFailure(2, Type::Text, Box::new(err)))?;
         Ok(QueueRecord {
             id,
+            queue,
             status: QueueStatus::from_string(&status)?,
             payload,
             attempts,
@@ -878,20 +1649,106 @@ impl SqliteQueue {
         })
     }
 
-    fn try_add_column(conn: &Connection, definition: &str) -> anyhow::Result<()> {
-        let statement = format!("ALTER TABLE change_queue ADD COLUMN {definition}");
-        let _ = conn.execute(&statement, []);
-        Ok(())
+    fn worker_from_row(row: &Row) -> rusqlite::Result<WorkerRecord> {
+        let capabilities: String = row.get(3)?;
+        let state: String = row.get(4)?;
+        let queues: String = row.get(9)?;
+        Ok(WorkerRecord {
+            worker_id: row.get(0)?,
+            hostname: row.get(1)?,
+            pid: row.get(2)?,
+            capabilities: serde_json::from_str(&capabilities)
+                .map_err(|err| Error::FromSqlConversionFailure(3, Type::Text, Box::new(err)))?,
+            queues: serde_json::from_str(&queues)
+                .map_err(|err| Error::FromSqlConversionFailure(9, Type::Text, Box::new(err)))?,
+            state: WorkerState::from_string(&state)?,
+            current_queue_id: row.get(5)?,
+            started_at: row.get(6)?,
+            heartbeat_at: row.get(7)?,
+            stopped_at: row.get(8)?,
+        })
+    }
+
+    fn log_from_row(row: &Row) -> rusqlite::Result<ChangeQueueLog> {
+        let details: Option<String> = row.get(5)?;
+        Ok(ChangeQueueLog {
+            id: row.get(0)?,
+            queue_id: row.get(1)?,
+            task_id: row.get(2)?,
+            level: row.get(3)?,
+            message: row.get(4)?,
+            details: details.and_then(|text| serde_json::from_str::<Value>(&text).ok()),
+            created_at: row.get(6)?,
+        })
     }
 }
 
-impl QueueStatus {
+impl AssignmentState {
+    fn from_string(value: &str) -> rusqlite::Result<Self> {
+        match value {
+            "pending" => Ok(Self::Pending),
+            "enqueued" => Ok(Self::Enqueued),
+            "agent_failed" => Ok(Self::AgentFailed),
+            "invalid" => Ok(Self::Invalid),
+            "cancelled" => Ok(Self::Cancelled),
+            _ => Err(Error::InvalidColumnName(format!(
+                "unknown assignment state: {value}"
+            ))),
+        }
+    }
+}
+
+fn rollup_assignments(assignments: &[TaskAssignmentRecord]) -> RequestRollup {
+    let (mut waiting, mut active, mut applied, mut failed, mut cancelled) = (0, 0, 0, 0, 0);
+    for assignment in assignments {
+        match (assignment.state, assignment.queue_status) {
+            (AssignmentState::AgentFailed | AssignmentState::Invalid, _) => failed += 1,
+            (AssignmentState::Cancelled, _) | (_, Some(QueueStatus::Cancelled)) => cancelled += 1,
+            (_, Some(QueueStatus::Failed)) => failed += 1,
+            (_, Some(QueueStatus::Applied)) => applied += 1,
+            (_, Some(QueueStatus::InProgress)) => active += 1,
+            _ => waiting += 1,
+        }
+    }
+    if waiting + active > 0 {
+        if active + applied + failed == 0 {
+            RequestRollup::Pending
+        } else {
+            RequestRollup::InProgress
+        }
+    } else if cancelled > 0 && applied + failed == 0 {
+        RequestRollup::Cancelled
+    } else if failed == 0 {
+        RequestRollup::Applied
+    } else if applied == 0 {
+        RequestRollup::Failed
+    } else {
+        RequestRollup::PartiallyFailed
+    }
+}
+
+impl WorkerState {
     fn from_string(value: &str) -> rusqlite::Result<Self> {
+        match value {
+            "idle" => Ok(Self::Idle),
+            "busy" => Ok(Self::Busy),
+            "paused" => Ok(Self::Paused),
+            "stopped" => Ok(Self::Stopped),
+            _ => Err(Error::InvalidColumnName(format!(
+                "unknown worker state: {value}"
+            ))),
+        }
+    }
+}
+
+impl QueueStatus {
+    pub(crate) fn from_string(value: &str) -> rusqlite::Result<Self> {
         match value {
             "pending" => Ok(Self::Pending),
             "in_progress" => Ok(Self::InProgress),
             "applied" => Ok(Self::Applied),
             "failed" => Ok(Self::Failed),
+            "cancelled" => Ok(Self::Cancelled),
             _ => Err(Error::InvalidColumnName(format!(
                 "unknown queue status: {value}"
             ))),
@@ -899,9 +1756,224 @@ impl QueueStatus {
     }
 }
 
+pub fn validate_queue_name(name: &str) -> anyhow::Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        anyhow::bail!("invalid queue name `{name}`: use letters, digits, `-` or `_`");
+    }
+    Ok(())
+}
+
 fn now_epoch() -> anyhow::Result<i64> {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .context("time went backwards")?;
     Ok(now.as_secs() as i64)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ChangeOperation, OperationKind};
+
+    fn request(task_id: &str, agent: &str) -> ChangeRequest {
+        ChangeRequest {
+            task_id: task_id.to_string(),
+            agent: agent.to_string(),
+            changes: vec![ChangeOperation {
+                path: format!("src/{task_id}.rs"),
+                operation: OperationKind::Update,
+                patch: format!("patch {task_id}"),
+                patch_hash: None,
+                base_hash: None,
+            }],
+            checks: vec![],
+            requires: vec![],
+        }
+    }
+
+    #[test]
+    fn paused_agents_are_skipped_and_mode_round_trips() {
+        let db = std::env::temp_dir().join(format!("hyperion-control-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&db);
+        let queue = SqliteQueue::open(&db).unwrap();
+        assert_eq!(queue.queue_mode().unwrap(), QueueMode::Running);
+        queue.set_queue_mode(QueueMode::Draining).unwrap();
+        assert_eq!(queue.queue_mode().unwrap(), QueueMode::Draining);
+
+        queue.enqueue(&request("CTL-1", "agent-a")).unwrap();
+        queue.enqueue(&request("CTL-2", "agent-b")).unwrap();
+        queue.set_agent_paused("agent-a", true).unwrap();
+        let lease = Duration::from_secs(30);
+        let record = queue.dequeue(lease, "worker-1").unwrap().unwrap();
+        assert_eq!(record.payload.task_id, "CTL-2");
+        assert!(queue.dequeue(lease, "worker-1").unwrap().is_none());
+        assert_eq!(queue.in_flight_count().unwrap(), 1);
+
+        queue.set_agent_paused("agent-a", false).unwrap();
+        assert!(queue.paused_agents().unwrap().is_empty());
+        let record = queue.dequeue(lease, "worker-1").unwrap().unwrap();
+        assert_eq!(record.payload.task_id, "CTL-1");
+        let _ = std::fs::remove_file(&db);
+    }
+
+    #[test]
+    fn cancels_pending_immediately_and_in_flight_on_request() {
+        let db = std::env::temp_dir().join(format!("hyperion-cancel-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&db);
+        let queue = SqliteQueue::open(&db).unwrap();
+        let pending = queue.enqueue(&request("CAN-1", "agent-a")).unwrap();
+        let in_flight = queue.enqueue(&request("CAN-2", "agent-a")).unwrap();
+
+        assert_eq!(
+            queue.cancel(pending).unwrap(),
+            Some(CancelOutcome::Cancelled)
+        );
+        let record = queue
+            .dequeue(Duration::from_secs(30), "worker-1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.id, in_flight);
+        assert_eq!(
+            queue.cancel(in_flight).unwrap(),
+            Some(CancelOutcome::Requested)
+        );
+        assert!(queue.cancel_requested(in_flight).unwrap());
+
+        queue
+            .mark_retry(in_flight, Some("apply failed".into()))
+            .unwrap();
+        let record = queue.get_record(in_flight).unwrap().unwrap();
+        assert_eq!(record.status, QueueStatus::Cancelled);
+        assert_eq!(
+            queue.cancel(in_flight).unwrap(),
+            Some(CancelOutcome::Finished(QueueStatus::Cancelled))
+        );
+        assert!(queue
+            .dequeue(Duration::from_secs(30), "worker-1")
+            .unwrap()
+            .is_none());
+        let _ = std::fs::remove_file(&db);
+    }
+
+    #[test]
+    fn registry_tracks_workers_and_flags_leases_of_dead_ones() {
+        let db = std::env::temp_dir().join(format!("hyperion-workers-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&db);
+        let queue = SqliteQueue::open(&db).unwrap();
+        let capabilities = WorkerCapabilities {
+            run_checks: true,
+            cargo: true,
+            network: false,
+            worktree: "shared".to_string(),
+            labels: vec!["node".to_string()],
+        };
+        queue
+            .register_worker("worker-1", "host-a", &capabilities)
+            .unwrap();
+        let id = queue.enqueue(&request("REG-1", "agent-a")).unwrap();
+        queue.dequeue(Duration::from_secs(30), "worker-1").unwrap();
+        queue
+            .worker_heartbeat("worker-1", WorkerState::Busy, Some(id))
+            .unwrap();
+
+        let workers = queue.list_workers().unwrap();
+        assert_eq!(workers.len(), 1);
+        assert_eq!(workers[0].capabilities, capabilities);
+        assert_eq!(workers[0].current_queue_id, Some(id));
+        assert!(queue.orphaned_leases(WORKER_STALE_SECS).unwrap().is_empty());
+
+        let orphaned = queue.orphaned_leases(-1).unwrap();
+        assert_eq!(orphaned.len(), 1);
+        assert_eq!(orphaned[0].queue_id, id);
+
+        queue
+            .worker_heartbeat("worker-1", WorkerState::Stopped, None)
+            .unwrap();
+        assert!(queue.list_workers().unwrap()[0].stopped_at.is_some());
+        assert_eq!(queue.orphaned_leases(WORKER_STALE_SECS).unwrap().len(), 1);
+        let _ = std::fs::remove_file(&db);
+    }
+
+    #[test]
+    fn dequeue_routes_required_labels_to_capable_workers() {
+        let db = std::env::temp_dir().join(format!("hyperion-routing-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&db);
+        let queue = SqliteQueue::open(&db).unwrap();
+        let capabilities = WorkerCapabilities {
+            run_checks: true,
+            cargo: false,
+            network: true,
+            worktree: "shared".to_string(),
+            labels: vec!["node".to_string()],
+        };
+        queue
+            .register_worker("worker-node", "host-a", &capabilities)
+            .unwrap();
+        let mut gpu = request("ROUTE-1", "agent-a");
+        gpu.requires = vec!["gpu".to_string()];
+        let gpu_id = queue.enqueue(&gpu).unwrap();
+        let mut node = request("ROUTE-2", "agent-a");
+        node.requires = vec!["node".to_string(), "network".to_string()];
+        let node_id = queue.enqueue(&node).unwrap();
+
+        let lease = Duration::from_secs(30);
+        assert!(queue.dequeue(lease, "unregistered").unwrap().is_none());
+        let record = queue.dequeue(lease, "worker-node").unwrap().unwrap();
+        assert_eq!(record.id, node_id);
+        assert!(queue.dequeue(lease, "worker-node").unwrap().is_none());
+
+        let unroutable = queue.unroutable().unwrap();
+        assert_eq!(unroutable.len(), 1);
+        assert_eq!(unroutable[0].id, gpu_id);
+        let _ = std::fs::remove_file(&db);
+    }
+
+    #[test]
+    fn named_queues_scope_reads_and_workers_follow_subscriptions() {
+        let db = std::env::temp_dir().join(format!("hyperion-named-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&db);
+        let team_a = SqliteQueue::open(&db).unwrap().scoped("team-a").unwrap();
+        let team_b = SqliteQueue::open(&db).unwrap().scoped("team-b").unwrap();
+        team_a.enqueue(&request("NS-1", "agent-a")).unwrap();
+        team_a.enqueue(&request("NS-2", "agent-a")).unwrap();
+        team_b.enqueue(&request("NS-3", "agent-b")).unwrap();
+        assert_eq!(team_a.list(QueueStatus::Pending).unwrap().len(), 2);
+        assert_eq!(team_b.list(QueueStatus::Pending).unwrap().len(), 1);
+        assert!(SqliteQueue::open(&db)
+            .unwrap()
+            .list(QueueStatus::Pending)
+            .unwrap()
+            .is_empty());
+        assert!(SqliteQueue::open(&db).unwrap().scoped("team a").is_err());
+
+        let lease = Duration::from_secs(30);
+        let record = team_b.dequeue(lease, "worker-b").unwrap().unwrap();
+        assert_eq!(
+            (record.queue.as_str(), record.payload.task_id.as_str()),
+            ("team-b", "NS-3")
+        );
+        assert!(team_b.dequeue(lease, "worker-b").unwrap().is_none());
+
+        team_b.set_queue_mode(QueueMode::Paused).unwrap();
+        let both = SqliteQueue::open(&db)
+            .unwrap()
+            .subscribed(&["team-a".to_string(), "team-b".to_string()])
+            .unwrap()
+            .with_max_in_flight(BTreeMap::from([("team-a".to_string(), 1)]));
+        assert_eq!(team_a.queue_mode().unwrap(), QueueMode::Running);
+        assert_eq!(both.subscription_mode().unwrap(), QueueMode::Running);
+        let record = both.dequeue_active(lease, "worker-ab").unwrap().unwrap();
+        assert_eq!(record.queue, "team-a");
+        assert!(both.dequeue_active(lease, "worker-ab").unwrap().is_none());
+
+        let metrics = team_a.queue_metrics(None).unwrap();
+        assert_eq!(metrics.status_counts.in_progress, 1);
+        assert_eq!(metrics.queues["team-a"].pending, 1);
+        assert_eq!(metrics.queues["team-b"].in_progress, 1);
+        let _ = std::fs::remove_file(&db);
+    }
+}
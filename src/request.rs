@@ -1,126 +1,214 @@
 use std::{
-    env, fs,
-    path::Path,
+    fs,
+    path::{Path, PathBuf},
     sync::{mpsc, Arc, Mutex},
     thread,
 };
 
 use anyhow::Context;
 use diffy::create_patch;
+use serde_json::json;
 use sha2::{Digest, Sha256};
 
-use crate::agent::{AgentHarness, CopilotHarness};
-use crate::models::{ChangeOperation, ChangeRequest, OperationKind, TaskAssignment, TaskRequest};
+use crate::agent::{AgentHarness, CopilotHarness, LimitedHarness, RecordingHarness, ReplayHarness};
+use crate::context;
+use crate::extract;
+use crate::limits::{AgentLimiter, AgentLimits};
+use crate::models::{
+    AgentSession, AssignmentState, ChangeOperation, ChangeRequest, OperationKind, TaskAssignment,
+    TaskRequest,
+};
 use crate::orchestrator;
+use crate::prompt::{self, PromptTemplates};
 use crate::queue::SqliteQueue;
 use crate::validator;
 
 pub const DEFAULT_MODEL: &str = "gpt-5-mini";
 
+#[derive(Debug, Clone, Default)]
+pub struct TranscriptOptions {
+    pub replay_dir: Option<PathBuf>,
+    pub record_dir: Option<PathBuf>,
+}
+
+pub struct RequestOptions {
+    pub harness: String,
+    pub model: Option<String>,
+    pub max_agents: usize,
+    pub transcripts: TranscriptOptions,
+    pub templates: PromptTemplates,
+    pub limits: AgentLimits,
+}
+
 pub fn handle_request(
     queue: &SqliteQueue,
     path: &Path,
-    model: Option<String>,
-    max_agents: usize,
+    options: RequestOptions,
 ) -> anyhow::Result<usize> {
     let contents = std::fs::read_to_string(path).context("read task request")?;
     let request: TaskRequest = serde_json::from_str(&contents).context("parse task request")?;
-    let assignments = orchestrator::decompose_request(&request);
+    let transcripts = &options.transcripts;
+    let copilot = options.harness == "copilot";
+    let session = if transcripts.replay_dir.is_none() && copilot {
+        queue.latest_agent_session()?
+    } else {
+        None
+    };
+    let limiter = Arc::new(AgentLimiter::new(options.limits));
+    let harness =
+        build_harness(copilot, options.model, session.as_ref(), transcripts)?.map(|harness| {
+            Box::new(LimitedHarness::new(harness, limiter.clone())) as Box<dyn AgentHarness>
+        });
+    let result = process_request(
+        queue,
+        &request,
+        harness.as_deref(),
+        &options.templates,
+        options.max_agents,
+    );
+    if let Some(session) = session {
+        let _ = queue.touch_agent_session(session.id);
+    }
+    result
+}
+
+fn build_harness(
+    copilot: bool,
+    model: Option<String>,
+    session: Option<&AgentSession>,
+    transcripts: &TranscriptOptions,
+) -> anyhow::Result<Option<Box<dyn AgentHarness>>> {
+    if let Some(dir) = transcripts.replay_dir.as_ref() {
+        return Ok(Some(Box::new(ReplayHarness::load(dir)?)));
+    }
+    if !copilot {
+        if transcripts.record_dir.is_some() {
+            anyhow::bail!("recording transcripts requires agents.harness = \"copilot\" (or HYPERION_AGENT=copilot)");
+        }
+        return Ok(None);
+    }
+    let model_name = model.unwrap_or_else(|| DEFAULT_MODEL.to_string());
+    let mut harness: Box<dyn AgentHarness> =
+        Box::new(CopilotHarness::with_session(model_name, session));
+    if let Some(dir) = transcripts.record_dir.as_ref() {
+        harness = Box::new(RecordingHarness::new(harness, dir)?);
+    }
+    Ok(Some(harness))
+}
+
+pub fn process_request(
+    queue: &SqliteQueue,
+    request: &TaskRequest,
+    harness: Option<&dyn AgentHarness>,
+    templates: &PromptTemplates,
+    max_agents: usize,
+) -> anyhow::Result<usize> {
+    let assignments = orchestrator::decompose_request(request);
     if assignments.is_empty() {
         return Err(anyhow::anyhow!("task request produced no assignments"));
     }
+    queue.record_task_request(request, &assignments)?;
 
-    let agent_count = max_agents.clamp(1, 3);
-    let receiver = Arc::new(Mutex::new(assignments.into_iter()));
+    let agent_count = max_agents.max(1);
+    let receiver = Mutex::new(assignments.into_iter());
     let (result_tx, result_rx) = mpsc::channel();
-    let mut handles = Vec::new();
-    let use_agents = env::var("HYPERION_AGENT").is_ok_and(|val| val == "copilot");
-    let session = if use_agents {
-        queue.latest_agent_session()?
-    } else {
-        None
-    };
-    let session_for_threads = session.clone();
-
-    for index in 0..agent_count {
-        let receiver = Arc::clone(&receiver);
-        let result_tx = result_tx.clone();
-        let agent_name = format!("agent-{}", index + 1);
-        let model_name = model.clone().unwrap_or_else(|| DEFAULT_MODEL.to_string());
-        let session_ref = session_for_threads.clone();
-        handles.push(thread::spawn(move || {
-            let harness = if use_agents {
-                Some(CopilotHarness::with_session(
-                    model_name.clone(),
-                    session_ref.as_ref(),
-                ))
-            } else {
-                None
-            };
-            loop {
-                let assignment = {
-                    let mut guard = receiver
-                        .lock()
-                        .map_err(|_| anyhow::anyhow!("assignment receiver lock poisoned"))?;
-                    guard.next()
-                };
-                match assignment {
-                    Some(assignment) => {
-                        let result = run_assignment(
-                            harness.as_ref().map(|h| h as &dyn AgentHarness),
-                            &assignment,
-                            &agent_name,
-                        );
-                        if result_tx.send(result).is_err() {
-                            break;
+
+    let mut failures = 0;
+    let mut enqueued = 0;
+    thread::scope(|scope| -> anyhow::Result<()> {
+        let mut handles = Vec::new();
+        for index in 0..agent_count {
+            let receiver = &receiver;
+            let result_tx = result_tx.clone();
+            let agent_name = format!("agent-{}", index + 1);
+            handles.push(scope.spawn(move || {
+                loop {
+                    let assignment = {
+                        let mut guard = receiver
+                            .lock()
+                            .map_err(|_| anyhow::anyhow!("assignment receiver lock poisoned"))?;
+                        guard.next()
+                    };
+                    match assignment {
+                        Some(assignment)
+                            if queue.assignment_state(&assignment.task_id)?
+                                == Some(AssignmentState::Cancelled) =>
+                        {
+                            continue;
                         }
+                        Some(assignment) => {
+                            let result =
+                                run_assignment(queue, harness, templates, &assignment, &agent_name);
+                            let outcome = (assignment.task_id, agent_name.clone(), result);
+                            if result_tx.send(outcome).is_err() {
+                                break;
+                            }
+                        }
+                        None => break,
                     }
-                    None => break,
                 }
-            }
-            Ok::<_, anyhow::Error>(())
-        }));
-    }
+                Ok::<_, anyhow::Error>(())
+            }));
+        }
 
-    drop(result_tx);
+        drop(result_tx);
 
-    let mut failures = 0;
-    let mut enqueued = 0;
-    for result in result_rx {
-        match result {
-            Ok(request) => {
-                let validation = validator::validate_change_request(&request);
-                if !validation.valid {
+        for (task_id, agent_name, result) in result_rx {
+            if queue.assignment_state(&task_id)? == Some(AssignmentState::Cancelled) {
+                println!("Skipping cancelled assignment {task_id}");
+                continue;
+            }
+            match result {
+                Ok(request) => {
+                    let validation = validator::validate_change_request(&request);
+                    if !validation.valid {
+                        failures += 1;
+                        eprintln!(
+                            "invalid change request for {}: {:?}",
+                            request.task_id, validation.errors
+                        );
+                        queue.update_assignment(
+                            &task_id,
+                            AssignmentState::Invalid,
+                            &agent_name,
+                            None,
+                            Some(format!("validation errors: {:?}", validation.errors)),
+                        )?;
+                        continue;
+                    }
+                    let id = queue.enqueue(&request)?;
+                    queue.update_assignment(
+                        &task_id,
+                        AssignmentState::Enqueued,
+                        &agent_name,
+                        Some(id),
+                        None,
+                    )?;
+                    println!("Enqueued change request {id} for {}", request.task_id);
+                    enqueued += 1;
+                }
+                Err(err) => {
                     failures += 1;
-                    eprintln!(
-                        "invalid change request for {}: {:?}",
-                        request.task_id, validation.errors
-                    );
-                    continue;
+                    eprintln!("agent failure: {err}");
+                    queue.update_assignment(
+                        &task_id,
+                        AssignmentState::AgentFailed,
+                        &agent_name,
+                        None,
+                        Some(err.to_string()),
+                    )?;
                 }
-                let id = queue.enqueue(&request)?;
-                println!("Enqueued change request {id} for {}", request.task_id);
-                enqueued += 1;
-            }
-            Err(err) => {
-                failures += 1;
-                eprintln!("agent failure: {err}");
             }
         }
-    }
 
-    for handle in handles {
-        if let Err(err) = handle.join() {
-            failures += 1;
-            eprintln!("agent thread panicked: {err:?}");
-        }
-    }
-
-    if use_agents {
-        if let Some(session) = session {
-            let _ = queue.touch_agent_session(session.id);
+        for handle in handles {
+            if let Err(err) = handle.join() {
+                failures += 1;
+                eprintln!("agent thread panicked: {err:?}");
+            }
         }
-    }
+        Ok(())
+    })?;
 
     if failures > 0 {
         return Err(anyhow::anyhow!("{} assignment(s) failed", failures));
@@ -130,27 +218,54 @@ pub fn handle_request(
 }
 
 fn run_assignment(
+    queue: &SqliteQueue,
     harness: Option<&dyn AgentHarness>,
+    templates: &PromptTemplates,
     assignment: &TaskAssignment,
     agent_name: &str,
 ) -> anyhow::Result<ChangeRequest> {
     if let Some(harness) = harness {
-        let prompt = build_prompt(assignment, agent_name);
-        if let Ok(response) = harness.run(&prompt) {
-            if let Ok(mut request) = serde_json::from_str::<ChangeRequest>(&response) {
-                request.task_id = assignment.task_id.clone();
-                request.agent = agent_name.to_string();
-                for change in request.changes.iter_mut() {
-                    if change.patch_hash.is_none() {
-                        change.patch_hash = Some(compute_patch_hash(&change.patch));
+        let prompt =
+            prompt::build_prompt(queue, templates, assignment, agent_name, harness.name())?;
+        match harness.run(&prompt) {
+            Ok(response) => match extract::extract_change_request(&response) {
+                Ok(mut request) => {
+                    request.task_id = assignment.task_id.clone();
+                    request.agent = agent_name.to_string();
+                    for change in request.changes.iter_mut() {
+                        if change.patch_hash.is_none() {
+                            change.patch_hash = Some(compute_patch_hash(&change.patch));
+                        }
                     }
+                    return Ok(request);
                 }
-                return Ok(request);
-            } else {
-                eprintln!("failed to parse agent response");
+                Err(failure) => {
+                    let _ = queue.log_event(
+                        0,
+                        &assignment.task_id,
+                        "warn",
+                        "agent response extraction failed",
+                        Some(&json!({
+                            "agent": agent_name,
+                            "error": failure.to_string(),
+                            "response_len": failure.response_len,
+                            "candidates": failure.candidates,
+                        })),
+                    );
+                }
+            },
+            Err(err) => {
+                let _ = queue.log_event(
+                    0,
+                    &assignment.task_id,
+                    "warn",
+                    "agent execution failed",
+                    Some(&json!({
+                        "agent": agent_name,
+                        "error": err.to_string(),
+                    })),
+                );
             }
-        } else {
-            eprintln!("agent execution failed");
         }
     }
     Ok(fallback_request(assignment, agent_name))
@@ -163,28 +278,10 @@ fn fallback_request(assignment: &TaskAssignment, agent_name: &str) -> ChangeRequ
         agent: agent_name.to_string(),
         changes: vec![change],
         checks: vec!["cargo fmt --check".to_string()],
+        requires: vec![],
     }
 }
 
-fn build_prompt(assignment: &TaskAssignment, agent_name: &str) -> String {
-    format!(
-        "You are {agent_name}. Produce a JSON change request only, no prose.\n\n\
-Task ID: {task_id}\n\
-Summary: {summary}\n\
-Files: {files}\n\
-Instructions:\n\
-- {instructions}\n\n\
-Return a single JSON object with fields: task_id, agent, changes (array), checks (array).\n\
-Each change must include: path, operation (add/update/delete), patch (diff or full replacement).\n\
-Include at least one check in the checks array.\n",
-        task_id = assignment.task_id,
-        summary = assignment.summary,
-        files = assignment.file_targets.join(", "),
-        instructions = assignment.instructions.join("\n- "),
-        agent_name = agent_name
-    )
-}
-
 fn build_change_operation(
     path: &str,
     assignment: &TaskAssignment,
@@ -222,6 +319,7 @@ index 0000000..0000000 100644\n\
         operation: OperationKind::Update,
         patch,
         patch_hash: Some(patch_hash),
+        base_hash: Some(context::sha256_hex(base_content.as_bytes())),
     }
 }
 
@@ -230,3 +328,64 @@ fn compute_patch_hash(patch: &str) -> String {
     hasher.update(patch.as_bytes());
     format!("{:x}", hasher.finalize())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{QueueStatus, RequestRollup, RequestedChange};
+
+    #[test]
+    fn replays_agent_transcript_into_queue() {
+        let root = std::env::temp_dir().join(format!("hyperion-request-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let fixtures = root.join("fixtures");
+        fs::create_dir_all(&fixtures).unwrap();
+
+        let request = TaskRequest {
+            request_id: "REQ-REPLAY".to_string(),
+            summary: "Replay a recorded agent response".to_string(),
+            requested_changes: vec![RequestedChange {
+                path: "src/lib.rs".to_string(),
+                summary: "Rename the greeting".to_string(),
+            }],
+        };
+        let assignment = &orchestrator::decompose_request(&request)[0];
+        let queue = SqliteQueue::open(&root.join("queue.db")).unwrap();
+        let templates = PromptTemplates::new(root.join("prompts"));
+        let prompt =
+            prompt::build_prompt(&queue, &templates, assignment, "agent-1", "copilot").unwrap();
+        let response = r#"{"task_id":"ignored","agent":"ignored","changes":[{"path":"src/lib.rs","operation":"update","patch":"--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-hello\n+goodbye\n"}],"checks":["cargo test"]}"#;
+        let transcript = crate::agent::AgentTranscript {
+            harness: Some("copilot".to_string()),
+            prompt_hash: crate::agent::prompt_hash(&prompt),
+            prompt,
+            response: response.to_string(),
+        };
+        fs::write(
+            fixtures.join("replay.json"),
+            serde_json::to_string(&transcript).unwrap(),
+        )
+        .unwrap();
+
+        let harness = ReplayHarness::load(&fixtures).unwrap();
+        let enqueued = process_request(&queue, &request, Some(&harness), &templates, 1).unwrap();
+        assert_eq!(enqueued, 1);
+
+        let records = queue.list(QueueStatus::Pending).unwrap();
+        assert_eq!(records.len(), 1);
+        let payload = &records[0].payload;
+        assert_eq!(payload.task_id, "REQ-REPLAY-1");
+        assert_eq!(payload.agent, "agent-1");
+        assert!(payload.changes[0].patch.contains("+goodbye"));
+        assert_eq!(payload.checks, vec!["cargo test".to_string()]);
+
+        let status = queue.request_status("REQ-REPLAY").unwrap().unwrap();
+        assert_eq!(status.rollup, RequestRollup::Pending);
+        assert_eq!(status.assignments[0].state, AssignmentState::Enqueued);
+        assert_eq!(status.assignments[0].queue_id, Some(records[0].id));
+        queue.mark_applied(records[0].id).unwrap();
+        let status = queue.request_status("REQ-REPLAY").unwrap().unwrap();
+        assert_eq!(status.rollup, RequestRollup::Applied);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -1,8 +1,12 @@
 use std::error::Error as StdError;
 use std::fmt;
-use std::process::{Command, Stdio};
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::Duration;
 
 use anyhow::Context;
+use serde::Serialize;
 use tracing::info;
 
 #[derive(Debug)]
@@ -39,27 +43,156 @@ impl StdError for CheckFailure {
     }
 }
 
+#[derive(Debug)]
+pub struct CheckCancelled {
+    pub command: String,
+}
+
+impl fmt::Display for CheckCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "check cancelled: {}", self.command)
+    }
+}
+
+impl StdError for CheckCancelled {}
+
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckOutput {
+    pub command: String,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+pub fn run_check(check: &str) -> anyhow::Result<CheckOutput> {
+    run_check_until(check, &|| false)
+}
+
+pub fn run_check_until(check: &str, cancelled: &dyn Fn() -> bool) -> anyhow::Result<CheckOutput> {
+    info!(command = %check, "running check");
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(check)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("run check: {check}"))?;
+    let stdout = drain_pipe(child.stdout.take());
+    let stderr = drain_pipe(child.stderr.take());
+    let status = loop {
+        if let Some(status) = child.try_wait().context("wait for check")? {
+            break status;
+        }
+        if cancelled() {
+            kill_check(&mut child);
+            return Err(CheckCancelled {
+                command: check.to_string(),
+            }
+            .into());
+        }
+        thread::sleep(CANCEL_POLL_INTERVAL);
+    };
+    Ok(CheckOutput {
+        command: check.to_string(),
+        success: status.success(),
+        exit_code: status.code(),
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn drain_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        String::from_utf8_lossy(&buffer).to_string()
+    })
+}
+
+fn kill_check(child: &mut Child) {
+    #[cfg(unix)]
+    let _ = Command::new("kill")
+        .arg("-KILL")
+        .arg(format!("-{}", child.id()))
+        .stderr(Stdio::null())
+        .status();
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
 pub fn run_checks(checks: &[String]) -> anyhow::Result<()> {
+    run_checks_until(checks, &|| false)
+}
+
+pub fn run_checks_until(checks: &[String], cancelled: &dyn Fn() -> bool) -> anyhow::Result<()> {
     for check in checks {
-        info!(command = %check, "running check");
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(check)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .with_context(|| format!("run check: {check}"))?;
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        if !output.status.success() {
+        let output = run_check_until(check, cancelled)?;
+        if !output.success {
             return Err(CheckFailure::new(
-                anyhow::anyhow!("check failed: {check} (exit {})", output.status),
-                check.clone(),
-                stdout,
-                stderr,
+                anyhow::anyhow!(
+                    "check failed: {check} (exit {})",
+                    output
+                        .exit_code
+                        .map_or_else(|| "signal".to_string(), |code| code.to_string())
+                ),
+                output.command,
+                output.stdout,
+                output.stderr,
             )
             .into());
         }
     }
     Ok(())
 }
+
+pub fn check_allowed(check: &str, allowed: &[String]) -> Result<(), String> {
+    let check = check.trim();
+    if check
+        .chars()
+        .any(|ch| matches!(ch, ';' | '|' | '&' | '`' | '$' | '>' | '<' | '\n'))
+    {
+        return Err(format!("`{check}` contains shell control characters"));
+    }
+    let permitted = allowed.iter().any(|prefix| {
+        check == prefix
+            || check
+                .strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.starts_with(' '))
+    });
+    if permitted {
+        Ok(())
+    } else {
+        Err(format!(
+            "`{check}` is not in checks.allowed ({})",
+            allowed.join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn cancelled_check_is_killed_promptly() {
+        let started = Instant::now();
+        let cancelled = || started.elapsed() > Duration::from_millis(200);
+        let err = run_checks_until(&["sleep 30".to_string()], &cancelled).unwrap_err();
+        assert!(err.downcast_ref::<CheckCancelled>().is_some());
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let output = run_check("echo ok").unwrap();
+        assert!(output.success);
+        assert_eq!(output.stdout.trim(), "ok");
+    }
+}
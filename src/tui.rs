@@ -2,8 +2,11 @@
 use std::{
     collections::VecDeque,
     io::{self, IsTerminal},
-    sync::{Arc, Mutex},
-    time::Duration,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 #[cfg(feature = "tui")]
@@ -21,29 +24,31 @@ use ratatui::{
 };
 
 #[cfg(feature = "tui")]
-use crate::models::QueueStatus;
+use crate::config::TuiSection;
 #[cfg(feature = "tui")]
-use crate::queue::SqliteQueue;
+use crate::models::{AssignmentState, QueueMode, QueueStatus, TaskRequestStatus};
+#[cfg(feature = "tui")]
+use crate::queue::{SqliteQueue, CONTROL_DESIRED_WORKERS, WORKER_STALE_SECS};
 #[cfg(feature = "tui")]
 use serde_json::Value;
 
 #[cfg(feature = "tui")]
-const STATUS_FILTERS: [Option<QueueStatus>; 5] = [
+const STATUS_FILTERS: [Option<QueueStatus>; 6] = [
     None,
     Some(QueueStatus::Pending),
     Some(QueueStatus::InProgress),
     Some(QueueStatus::Applied),
     Some(QueueStatus::Failed),
+    Some(QueueStatus::Cancelled),
 ];
 
-#[cfg(feature = "tui")]
-const REFRESH_INTERVALS: [u64; 4] = [200, 500, 1000, 2000];
-
 #[cfg(feature = "tui")]
 pub struct TuiConfig {
     pub db_path: String,
-    pub worker_count: usize,
+    pub worker_count: Arc<AtomicUsize>,
+    pub max_workers: usize,
     pub agent_count: usize,
+    pub refresh_intervals_ms: Vec<u64>,
     pub modified_files: Arc<Mutex<VecDeque<String>>>,
 }
 
@@ -55,6 +60,8 @@ struct TuiState {
     selected_index: usize,
     show_detail: bool,
     show_events: bool,
+    show_tree: bool,
+    show_workers: bool,
 }
 
 #[cfg(feature = "tui")]
@@ -67,18 +74,22 @@ impl Default for TuiState {
             selected_index: 0,
             show_detail: true,
             show_events: true,
+            show_tree: false,
+            show_workers: false,
         }
     }
 }
 
 #[cfg(feature = "tui")]
-pub fn run_dashboard(queue: &SqliteQueue) -> anyhow::Result<()> {
+pub fn run_dashboard(queue: &SqliteQueue, settings: &TuiSection) -> anyhow::Result<()> {
     run_dashboard_with_config(
         queue,
         TuiConfig {
             db_path: "hyperion.db".to_string(),
-            worker_count: 0,
+            worker_count: Arc::new(AtomicUsize::new(0)),
+            max_workers: 0,
             agent_count: 0,
+            refresh_intervals_ms: settings.refresh_intervals_ms.clone(),
             modified_files: Arc::new(Mutex::new(VecDeque::new())),
         },
     )
@@ -96,7 +107,15 @@ pub fn run_dashboard_with_config(queue: &SqliteQueue, config: TuiConfig) -> anyh
     execute!(stdout, EnterAlternateScreen)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
-    let mut state = TuiState::default();
+    let refresh_intervals = if config.refresh_intervals_ms.is_empty() {
+        vec![500]
+    } else {
+        config.refresh_intervals_ms.clone()
+    };
+    let mut state = TuiState {
+        refresh_index: 1.min(refresh_intervals.len() - 1),
+        ..TuiState::default()
+    };
 
     loop {
         let pending_records = queue.list(QueueStatus::Pending).unwrap_or_default();
@@ -109,8 +128,27 @@ pub fn run_dashboard_with_config(queue: &SqliteQueue, config: TuiConfig) -> anyh
             .list(QueueStatus::Failed)
             .map(|records| records.len())
             .unwrap_or(0usize);
+        let cancelled_count = queue
+            .list(QueueStatus::Cancelled)
+            .map(|records| records.len())
+            .unwrap_or(0usize);
         let dead_letters = queue.dead_letter_count().unwrap_or(0);
+        let unroutable_count = queue.unroutable().map(|records| records.len()).unwrap_or(0);
         let history_records = queue.recent_records(100).unwrap_or_default();
+        let request_statuses = if state.show_tree {
+            queue.recent_request_statuses(10).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let workers = if state.show_workers {
+            queue.list_workers().unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or_default();
         let metrics = queue.queue_metrics(Some(60)).unwrap_or_default();
         let format_metric = |value: Option<f64>, suffix: &str| {
             value
@@ -169,11 +207,13 @@ pub fn run_dashboard_with_config(queue: &SqliteQueue, config: TuiConfig) -> anyh
                 .split(header_rows[0]);
 
             let summary_text = format!(
-                "Queue Overview\nPending: {}\nIn Progress: {}\nApplied: {}\nFailed: {}\nDead Letters: {}",
+                "Pending: {}  In Progress: {}\nApplied: {}  Failed: {}  Cancelled: {}\nUnroutable: {}  Dead Letters: {}",
                 pending_records.len(),
                 in_progress_records.len(),
                 applied_count,
                 failed_count,
+                cancelled_count,
+                unroutable_count,
                 dead_letters
             );
             let summary = Paragraph::new(summary_text)
@@ -187,9 +227,31 @@ pub fn run_dashboard_with_config(queue: &SqliteQueue, config: TuiConfig) -> anyh
                 ),
                 _ => "Session: <none>".to_string(),
             };
+            let desired_workers = queue.desired_workers().ok().flatten();
+            let workers_text = match desired_workers {
+                Some(target) => format!(
+                    "{} (target {target}, max {})",
+                    config.worker_count.load(Ordering::SeqCst),
+                    config.max_workers
+                ),
+                None => config.worker_count.load(Ordering::SeqCst).to_string(),
+            };
+            let mode = queue.queue_mode().unwrap_or_default();
+            let paused_agents = queue.paused_agents().unwrap_or_default();
+            let mode_text = if paused_agents.is_empty() {
+                mode.as_str().to_string()
+            } else {
+                let names: Vec<&str> = paused_agents.iter().map(String::as_str).collect();
+                format!("{} (paused agents: {})", mode.as_str(), names.join(", "))
+            };
             let runtime_text = format!(
-                "Runtime Insights\nDB: {}\nWorkers: {}\nAgents: {}\n{}",
-                config.db_path, config.worker_count, config.agent_count, session_text
+                "Runtime Insights\nDB: {} (queue {})\nMode: {}\nWorkers: {}\nAgents: {}\n{}",
+                config.db_path,
+                queue.name(),
+                mode_text,
+                workers_text,
+                config.agent_count,
+                session_text
             );
             let runtime = Paragraph::new(runtime_text)
                 .block(Block::default().title("Runtime").borders(Borders::ALL));
@@ -205,10 +267,12 @@ pub fn run_dashboard_with_config(queue: &SqliteQueue, config: TuiConfig) -> anyh
                 .unwrap_or("all agents")
                 .to_string();
             let guidance_text = format!(
-                "Controls\nq: Quit\ns: Cycle status filter ({status_label})\na: Cycle agent ({agent_label})\nr: Refresh {refresh}ms\nd: Toggle detail pane ({})\ne: Toggle events ({})\narrow/↓: move selection\nhyperion request <file>: enqueue task request\nhyperion session init --resume=<token> [--model=<name>] [--allow-all-tools=<bool>]\nhyperion session list: show stored Copilot sessions\nhyperion queue-metrics --format json --since {window}: export throughput/latency/lease stats\n`hyperion run` / `hyperion worker` print `[progress]` lines with the same metrics before the TUI opens\n",
+                "Controls\nq: Quit\ns: Cycle status filter ({status_label})\na: Cycle agent ({agent_label})\nr: Refresh {refresh}ms\nd: Toggle detail pane ({})\ne: Toggle events ({})\nt: Toggle request tree ({})\nw: Toggle workers pane ({})\n+/-: Scale workers\np: Pause/resume queue\nx: Drain queue\nP: Pause/resume selected agent\narrow/↓: move selection\nhyperion request <file>: enqueue task request\nhyperion session init --resume=<token> [--model=<name>] [--allow-all-tools=<bool>]\nhyperion session list: show stored Copilot sessions\nhyperion queue-metrics --format json --since {window}: export throughput/latency/lease stats\n`hyperion run` / `hyperion worker` print `[progress]` lines with the same metrics before the TUI opens\n",
                 if state.show_detail { "on" } else { "off" },
                 if state.show_events { "on" } else { "off" },
-                refresh = REFRESH_INTERVALS[state.refresh_index],
+                if state.show_tree { "on" } else { "off" },
+                if state.show_workers { "on" } else { "off" },
+                refresh = refresh_intervals[state.refresh_index],
                 window = metrics.window_seconds,
             );
             let guidance = Paragraph::new(guidance_text)
@@ -278,33 +342,73 @@ pub fn run_dashboard_with_config(queue: &SqliteQueue, config: TuiConfig) -> anyh
                 );
             frame.render_widget(queue_table, queue_middle_chunks[0]);
 
-            let history_rows: Vec<Row> = history_records
-                .iter()
-                .take(8)
-                .map(|record| {
-                    Row::new(vec![
-                        record.payload.task_id.clone(),
-                        record.payload.agent.clone(),
-                        record.status.as_str().to_string(),
-                        record.attempts.to_string(),
-                    ])
-                })
-                .collect();
-
-            let history_widths = [
-                Constraint::Length(20),
-                Constraint::Length(12),
-                Constraint::Length(12),
-                Constraint::Length(10),
-            ];
-            let history_table = Table::new(history_rows, history_widths)
-                .header(Row::new(vec!["Task ID", "Agent", "Status", "Attempts"]))
-                .block(
-                    Block::default()
-                        .title("Task History (last 100)")
-                        .borders(Borders::ALL),
-                );
-            frame.render_widget(history_table, queue_middle_chunks[1]);
+            if state.show_workers {
+                let worker_rows: Vec<Row> = workers
+                    .iter()
+                    .map(|worker| {
+                        let state = if worker.is_dead(now, WORKER_STALE_SECS) {
+                            "dead"
+                        } else {
+                            worker.state.as_str()
+                        };
+                        Row::new(vec![
+                            worker.worker_id.clone(),
+                            state.to_string(),
+                            worker
+                                .current_queue_id
+                                .map_or_else(|| "-".to_string(), |id| id.to_string()),
+                            format!("{}s", now - worker.heartbeat_at),
+                        ])
+                    })
+                    .collect();
+                let worker_widths = [
+                    Constraint::Length(16),
+                    Constraint::Length(8),
+                    Constraint::Length(8),
+                    Constraint::Length(10),
+                ];
+                let worker_table = Table::new(worker_rows, worker_widths)
+                    .header(Row::new(vec!["Worker", "State", "Queue", "Heartbeat"]))
+                    .block(Block::default().title("Workers").borders(Borders::ALL));
+                frame.render_widget(worker_table, queue_middle_chunks[1]);
+            } else {
+                let history_rows: Vec<Row> = if state.show_tree {
+                    request_tree_rows(&request_statuses)
+                } else {
+                    history_records
+                        .iter()
+                        .take(8)
+                        .map(|record| {
+                            Row::new(vec![
+                                record.payload.task_id.clone(),
+                                record.payload.agent.clone(),
+                                record.status.as_str().to_string(),
+                                record.attempts.to_string(),
+                            ])
+                        })
+                        .collect()
+                };
+
+                let history_widths = [
+                    Constraint::Length(20),
+                    Constraint::Length(12),
+                    Constraint::Length(16),
+                    Constraint::Length(10),
+                ];
+                let (history_title, history_header) = if state.show_tree {
+                    ("Task Requests (tree)", vec!["Request/Task", "Agent", "Status", "Attempts"])
+                } else {
+                    ("Task History (last 100)", vec!["Task ID", "Agent", "Status", "Attempts"])
+                };
+                let history_table = Table::new(history_rows, history_widths)
+                    .header(Row::new(history_header))
+                    .block(
+                        Block::default()
+                            .title(history_title)
+                            .borders(Borders::ALL),
+                    );
+                frame.render_widget(history_table, queue_middle_chunks[1]);
+            }
 
             let bottom_chunks = Layout::default()
                 .direction(Direction::Horizontal)
@@ -468,7 +572,7 @@ pub fn run_dashboard_with_config(queue: &SqliteQueue, config: TuiConfig) -> anyh
             frame.render_widget(modified_para, details_chunks[2]);
         })?;
 
-        let refresh_duration = Duration::from_millis(REFRESH_INTERVALS[state.refresh_index]);
+        let refresh_duration = Duration::from_millis(refresh_intervals[state.refresh_index]);
         if event::poll(refresh_duration)? {
             if let Event::Key(key) = event::read()? {
                 match key.code {
@@ -483,7 +587,7 @@ pub fn run_dashboard_with_config(queue: &SqliteQueue, config: TuiConfig) -> anyh
                         state.selected_index = 0;
                     }
                     KeyCode::Char('r') => {
-                        state.refresh_index = (state.refresh_index + 1) % REFRESH_INTERVALS.len();
+                        state.refresh_index = (state.refresh_index + 1) % refresh_intervals.len();
                     }
                     KeyCode::Char('d') => {
                         state.show_detail = !state.show_detail;
@@ -491,17 +595,47 @@ pub fn run_dashboard_with_config(queue: &SqliteQueue, config: TuiConfig) -> anyh
                     KeyCode::Char('e') => {
                         state.show_events = !state.show_events;
                     }
-                    KeyCode::Up => {
-                        if state.selected_index > 0 {
-                            state.selected_index -= 1;
-                        }
+                    KeyCode::Char('t') => {
+                        state.show_tree = !state.show_tree;
+                    }
+                    KeyCode::Char('w') => {
+                        state.show_workers = !state.show_workers;
+                    }
+                    KeyCode::Char('+') | KeyCode::Char('-') if config.max_workers > 0 => {
+                        let current = queue
+                            .desired_workers()?
+                            .unwrap_or_else(|| config.worker_count.load(Ordering::SeqCst));
+                        let target = if key.code == KeyCode::Char('+') {
+                            (current + 1).min(config.max_workers)
+                        } else {
+                            current.saturating_sub(1).max(1)
+                        };
+                        queue.set_control(CONTROL_DESIRED_WORKERS, &target.to_string())?;
+                    }
+                    KeyCode::Char('p') => {
+                        let next = if queue.queue_mode()?.accepts_work() {
+                            QueueMode::Paused
+                        } else {
+                            QueueMode::Running
+                        };
+                        queue.set_queue_mode(next)?;
+                    }
+                    KeyCode::Char('x') => {
+                        queue.set_queue_mode(QueueMode::Draining)?;
                     }
-                    KeyCode::Down => {
-                        if !queue_records.is_empty() {
-                            state.selected_index =
-                                (state.selected_index + 1).min(queue_records.len() - 1);
+                    KeyCode::Char('P') => {
+                        if let Some(agent) = state.agent_filter.as_deref() {
+                            let paused = queue.paused_agents()?.contains(agent);
+                            queue.set_agent_paused(agent, !paused)?;
                         }
                     }
+                    KeyCode::Up if state.selected_index > 0 => {
+                        state.selected_index -= 1;
+                    }
+                    KeyCode::Down if !queue_records.is_empty() => {
+                        state.selected_index =
+                            (state.selected_index + 1).min(queue_records.len() - 1);
+                    }
                     _ => {}
                 }
             }
@@ -514,6 +648,40 @@ pub fn run_dashboard_with_config(queue: &SqliteQueue, config: TuiConfig) -> anyh
     Ok(())
 }
 
+#[cfg(feature = "tui")]
+fn request_tree_rows(statuses: &[TaskRequestStatus]) -> Vec<Row<'static>> {
+    let mut rows = Vec::new();
+    for status in statuses {
+        let applied = status
+            .assignments
+            .iter()
+            .filter(|assignment| assignment.queue_status == Some(QueueStatus::Applied))
+            .count();
+        rows.push(Row::new(vec![
+            status.request_id.clone(),
+            format!("{}/{}", applied, status.assignments.len()),
+            status.rollup.as_str().to_string(),
+            String::new(),
+        ]));
+        for assignment in &status.assignments {
+            let state = match (assignment.state, assignment.queue_status) {
+                (AssignmentState::Enqueued, Some(queue_status)) => queue_status.as_str(),
+                (state, _) => state.as_str(),
+            };
+            rows.push(Row::new(vec![
+                format!(" └ {}", assignment.task_id),
+                assignment.agent.clone().unwrap_or_default(),
+                state.to_string(),
+                assignment
+                    .attempts
+                    .map(|attempts| attempts.to_string())
+                    .unwrap_or_default(),
+            ]));
+        }
+    }
+    rows
+}
+
 fn truncate(value: &str, max: usize) -> String {
     if value.len() <= max {
         value.to_string()
@@ -541,7 +709,10 @@ fn cycle_agent_filter(names: &[String], current: Option<&str>) -> Option<String>
 }
 
 #[cfg(not(feature = "tui"))]
-pub fn run_dashboard(_queue: &crate::queue::SqliteQueue) -> anyhow::Result<()> {
+pub fn run_dashboard(
+    _queue: &crate::queue::SqliteQueue,
+    _settings: &crate::config::TuiSection,
+) -> anyhow::Result<()> {
     Err(anyhow::anyhow!(
         "tui feature not enabled; rebuild with --features tui"
     ))
@@ -550,8 +721,11 @@ pub fn run_dashboard(_queue: &crate::queue::SqliteQueue) -> anyhow::Result<()> {
 #[cfg(not(feature = "tui"))]
 pub struct TuiConfig {
     pub db_path: String,
-    pub worker_count: usize,
+    pub worker_count: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    pub max_workers: usize,
     pub agent_count: usize,
+    pub refresh_intervals_ms: Vec<u64>,
+    pub modified_files: std::sync::Arc<std::sync::Mutex<std::collections::VecDeque<String>>>,
 }
 
 #[cfg(not(feature = "tui"))]
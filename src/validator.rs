@@ -17,6 +17,13 @@ pub fn validate_change_request(request: &ChangeRequest) -> ValidationResult {
     if request.checks.is_empty() {
         errors.push("checks must not be empty".to_string());
     }
+    for (index, label) in request.requires.iter().enumerate() {
+        if label.trim().is_empty() || label.trim() != label {
+            errors.push(format!(
+                "requires[{index}] must be a non-empty label without surrounding whitespace"
+            ));
+        }
+    }
     for (index, change) in request.changes.iter().enumerate() {
         validate_change_operation(index, change, &mut errors);
         validate_patch_alignment(index, change, &mut errors);
@@ -128,8 +135,10 @@ mod tests {
                 operation: OperationKind::Update,
                 patch: "".to_string(),
                 patch_hash: Some(patch_hash("")),
+                base_hash: None,
             }],
             checks: vec![],
+            requires: vec![],
         };
 
         let result = validate_change_request(&request);
@@ -158,7 +167,9 @@ mod tests {
                 operation: OperationKind::Update,
                 patch: patch.clone(),
                 patch_hash: Some(patch_hash(&patch)),
+                base_hash: None,
             }],
+            requires: vec![],
             checks: vec!["cargo test".to_string()],
         };
 
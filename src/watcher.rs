@@ -1,5 +1,8 @@
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::channel;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::channel,
+};
 use std::time::Duration;
 
 use anyhow::Context;
@@ -9,11 +12,19 @@ use crate::models::ChangeRequest;
 use crate::queue::SqliteQueue;
 
 pub fn watch_directory(queue: &SqliteQueue, path: &Path) -> anyhow::Result<()> {
+    watch_directory_until(queue, path, &AtomicBool::new(true))
+}
+
+pub fn watch_directory_until(
+    queue: &SqliteQueue,
+    path: &Path,
+    running: &AtomicBool,
+) -> anyhow::Result<()> {
     let (tx, rx) = channel();
     let mut watcher = recommended_watcher(tx).context("create file watcher")?;
     watcher.watch(path, RecursiveMode::NonRecursive)?;
 
-    loop {
+    while running.load(Ordering::SeqCst) {
         match rx.recv_timeout(Duration::from_secs(1)) {
             Ok(event) => match event {
                 Ok(event) => {
@@ -31,6 +42,7 @@ pub fn watch_directory(queue: &SqliteQueue, path: &Path) -> anyhow::Result<()> {
             Err(err) => return Err(err.into()),
         }
     }
+    Ok(())
 }
 
 fn ingest_change_request(queue: &SqliteQueue, path: &PathBuf) -> anyhow::Result<()> {
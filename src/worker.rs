@@ -1,3 +1,5 @@
+use std::cell::Cell;
+use std::process::{Command, Stdio};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
@@ -8,6 +10,8 @@ use anyhow::Error;
 use tracing::{info, warn};
 
 use crate::apply;
+use crate::config::HyperionConfig;
+use crate::models::{QueueMode, QueueRecord, WorkerCapabilities, WorkerState};
 use crate::queue::SqliteQueue;
 use crate::runner;
 use crate::validator;
@@ -19,6 +23,7 @@ pub struct WorkerConfig {
     pub poll_interval_ms: u64,
     pub run_checks: bool,
     pub max_attempts: i64,
+    pub capabilities: WorkerCapabilities,
 }
 
 pub fn run_worker(queue: &SqliteQueue, config: WorkerConfig) -> anyhow::Result<()> {
@@ -31,6 +36,7 @@ pub fn run_worker(queue: &SqliteQueue, config: WorkerConfig) -> anyhow::Result<(
 }
 
 const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+const CHECK_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
 
 pub fn run_worker_with_signal(
     queue: &SqliteQueue,
@@ -42,10 +48,13 @@ pub fn run_worker_with_signal(
         lease_seconds = config.lease_seconds,
         poll_interval_ms = config.poll_interval_ms,
         run_checks = config.run_checks,
+        queues = ?queue.subscriptions(),
         "worker started"
     );
 
+    queue.register_worker(&config.worker_id, &local_hostname(), &config.capabilities)?;
     let mut next_progress = Instant::now();
+    let mut mode = QueueMode::Running;
     while running.load(Ordering::SeqCst) {
         let now = Instant::now();
         if config.worker_id == "worker-cli" && now >= next_progress {
@@ -54,10 +63,27 @@ pub fn run_worker_with_signal(
             }
             next_progress = now + PROGRESS_INTERVAL;
         }
+        let current_mode = queue.subscription_mode()?;
+        if current_mode != mode {
+            info!(worker_id = %config.worker_id, mode = current_mode.as_str(), "queue mode changed");
+            mode = current_mode;
+        }
+        let idle_state = if mode.accepts_work() {
+            WorkerState::Idle
+        } else {
+            WorkerState::Paused
+        };
+        queue.worker_heartbeat(&config.worker_id, idle_state, None)?;
+        if !mode.accepts_work() {
+            std::thread::sleep(Duration::from_millis(config.poll_interval_ms));
+            continue;
+        }
         let dequeue_start = Instant::now();
-        let record = queue.dequeue(Duration::from_secs(config.lease_seconds), &config.worker_id)?;
+        let record =
+            queue.dequeue_active(Duration::from_secs(config.lease_seconds), &config.worker_id)?;
         let dequeue_duration = dequeue_start.elapsed();
         if let Some(record) = record {
+            queue.worker_heartbeat(&config.worker_id, WorkerState::Busy, Some(record.id))?;
             let _ = queue.log_event(
                 record.id,
                 &record.payload.task_id,
@@ -121,6 +147,10 @@ pub fn run_worker_with_signal(
                 continue;
             }
 
+            if cancel_if_requested(queue, &record, "apply", false)? {
+                continue;
+            }
+
             let apply_start = Instant::now();
             if let Err(err) = apply::apply_change_request(&record.payload) {
                 let _ = queue.log_event(
@@ -139,8 +169,28 @@ pub fn run_worker_with_signal(
                 continue;
             }
 
+            if cancel_if_requested(queue, &record, "checks", true)? {
+                continue;
+            }
+
             if config.run_checks {
-                if let Err(err) = runner::run_checks(&record.payload.checks) {
+                let last_heartbeat = Cell::new(Instant::now());
+                let cancelled = || {
+                    if last_heartbeat.get().elapsed() >= CHECK_HEARTBEAT_INTERVAL {
+                        let _ = queue.worker_heartbeat(
+                            &config.worker_id,
+                            WorkerState::Busy,
+                            Some(record.id),
+                        );
+                        last_heartbeat.set(Instant::now());
+                    }
+                    queue.cancel_requested(record.id).unwrap_or(false)
+                };
+                if let Err(err) = runner::run_checks_until(&record.payload.checks, &cancelled) {
+                    if err.downcast_ref::<runner::CheckCancelled>().is_some() {
+                        mark_cancelled(queue, &record, "checks", true)?;
+                        continue;
+                    }
                     let _ = queue.log_event(
                         record.id,
                         &record.payload.task_id,
@@ -183,10 +233,67 @@ pub fn run_worker_with_signal(
         }
     }
 
+    queue.worker_heartbeat(&config.worker_id, WorkerState::Stopped, None)?;
     info!("worker shutting down");
     Ok(())
 }
 
+pub fn detect_capabilities(settings: &HyperionConfig) -> WorkerCapabilities {
+    let cargo = Command::new("cargo")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    WorkerCapabilities {
+        run_checks: settings.checks.enabled,
+        cargo,
+        network: settings.worker.network,
+        worktree: settings.worker.worktree.clone(),
+        labels: settings.worker.labels.clone(),
+    }
+}
+
+fn local_hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+fn cancel_if_requested(
+    queue: &SqliteQueue,
+    record: &QueueRecord,
+    stage: &str,
+    applied: bool,
+) -> anyhow::Result<bool> {
+    if !queue.cancel_requested(record.id)? {
+        return Ok(false);
+    }
+    mark_cancelled(queue, record, stage, applied)?;
+    Ok(true)
+}
+
+fn mark_cancelled(
+    queue: &SqliteQueue,
+    record: &QueueRecord,
+    stage: &str,
+    applied: bool,
+) -> anyhow::Result<()> {
+    queue.mark_cancelled(record.id, &format!("cancelled by operator during {stage}"))?;
+    let _ = queue.log_event(
+        record.id,
+        &record.payload.task_id,
+        "warn",
+        "cancelled",
+        Some(&json!({"stage": stage, "applied": applied})),
+    );
+    warn!(task_id = %record.payload.task_id, stage, applied, "change request cancelled");
+    Ok(())
+}
+
 fn report_progress(queue: &SqliteQueue) -> anyhow::Result<()> {
     let metrics = queue.queue_metrics(Some(60))?;
     let counts = metrics.status_counts;